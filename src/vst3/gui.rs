@@ -42,6 +42,9 @@
 //! ```
 
 use super::ffi;
+use super::plugframe::{IPlugFrame, PlugFrame};
+#[cfg(target_os = "linux")]
+use super::runloop::{IRunLoop, RunLoop};
 use super::Vst3Plugin;
 use crate::error::{Error, Result};
 use std::ffi::CString;
@@ -68,6 +71,20 @@ use std::marker::PhantomData;
 /// The plugin instance must outlive the GUI.
 pub struct Vst3Gui {
     handle: *mut ffi::RackVST3Gui,
+    /// Host-side run loop driven by [`pump_events`](Vst3Gui::pump_events).
+    ///
+    /// The `RunLoop` is boxed so its address is stable; the `IRunLoop` COM
+    /// object handed to the plugin's view holds a raw pointer into it, so both
+    /// must live exactly as long as the GUI.
+    #[cfg(target_os = "linux")]
+    run_loop: Box<RunLoop>,
+    #[cfg(target_os = "linux")]
+    _irunloop: Box<IRunLoop>,
+    /// Host-side plug frame handed to the view so plugin-initiated resizes are
+    /// honored. Boxed for a stable address: the [`IPlugFrame`] COM object given
+    /// to the view holds a raw pointer into it, so both live as long as the GUI.
+    plug_frame: Box<PlugFrame>,
+    _iplugframe: Box<IPlugFrame>,
     _marker: PhantomData<*mut ()>, // !Send + !Sync
 }
 
@@ -114,8 +131,107 @@ impl Vst3Gui {
                     "Failed to create GUI: plugin may not support GUI or display unavailable".into(),
                 ));
             }
+
+            // Hand the view a frame so it can request resizes of itself.
+            let mut plug_frame = Box::new(PlugFrame::new(handle));
+            let iplugframe = IPlugFrame::new(plug_frame.as_mut() as *mut PlugFrame);
+            ffi::rack_vst3_gui_set_plug_frame(
+                handle,
+                iplugframe.as_ref() as *const IPlugFrame as *mut std::ffi::c_void,
+            );
+
+            #[cfg(target_os = "linux")]
+            {
+                let mut run_loop = Box::new(RunLoop::new());
+                let irunloop = IRunLoop::new(run_loop.as_mut() as *mut RunLoop);
+                // Hand the run loop to the view so JUCE/VSTGUI editors can
+                // register their X11 fds and timers with the host.
+                ffi::rack_vst3_gui_set_run_loop(
+                    handle,
+                    irunloop.as_ref() as *const IRunLoop as *mut std::ffi::c_void,
+                );
+                Ok(Self {
+                    handle,
+                    run_loop,
+                    _irunloop: irunloop,
+                    plug_frame,
+                    _iplugframe: iplugframe,
+                    _marker: PhantomData,
+                })
+            }
+
+            #[cfg(not(target_os = "linux"))]
+            Ok(Self {
+                handle,
+                plug_frame,
+                _iplugframe: iplugframe,
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    /// Create a GUI embedded in a host-provided parent window
+    ///
+    /// Unlike [`create`](Vst3Gui::create), which spawns a standalone top-level
+    /// window, this reparents the plugin's view under a caller-supplied native
+    /// parent (an X11 `Window` on Linux, an `HWND` on Windows) — the "GUI
+    /// swallowing" technique used by traditional FST-based hosts. This is what
+    /// lets a host application host the editor inside its own UI rather than
+    /// reading back [`get_window_id`](Vst3Gui::get_window_id) after the fact.
+    ///
+    /// The embedded path skips window decorations and honors the parent's
+    /// coordinate origin when positioning the plugin's reported view rect.
+    /// [`pump_events`](Vst3Gui::pump_events) and the IRunLoop descriptors are
+    /// routed exactly as for a standalone window.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin` - An initialized VST3 plugin instance
+    /// * `parent_window_id` - Native parent window handle (X11 `Window` / `HWND`)
+    ///
+    /// # Thread Safety
+    ///
+    /// Must be called from the main thread.
+    pub fn create_embedded(plugin: &mut Vst3Plugin, parent_window_id: u64) -> Result<Self> {
+        unsafe {
+            let handle = ffi::rack_vst3_gui_create_embedded(plugin.as_ptr(), parent_window_id);
+            if handle.is_null() {
+                return Err(Error::Other(
+                    "Failed to create embedded GUI: plugin may not support GUI or parent invalid"
+                        .into(),
+                ));
+            }
+
+            let mut plug_frame = Box::new(PlugFrame::new(handle));
+            let iplugframe = IPlugFrame::new(plug_frame.as_mut() as *mut PlugFrame);
+            ffi::rack_vst3_gui_set_plug_frame(
+                handle,
+                iplugframe.as_ref() as *const IPlugFrame as *mut std::ffi::c_void,
+            );
+
+            #[cfg(target_os = "linux")]
+            {
+                let mut run_loop = Box::new(RunLoop::new());
+                let irunloop = IRunLoop::new(run_loop.as_mut() as *mut RunLoop);
+                ffi::rack_vst3_gui_set_run_loop(
+                    handle,
+                    irunloop.as_ref() as *const IRunLoop as *mut std::ffi::c_void,
+                );
+                Ok(Self {
+                    handle,
+                    run_loop,
+                    _irunloop: irunloop,
+                    plug_frame,
+                    _iplugframe: iplugframe,
+                    _marker: PhantomData,
+                })
+            }
+
+            #[cfg(not(target_os = "linux"))]
             Ok(Self {
                 handle,
+                plug_frame,
+                _iplugframe: iplugframe,
                 _marker: PhantomData,
             })
         }
@@ -238,6 +354,16 @@ impl Vst3Gui {
     /// This must be called regularly to keep the GUI responsive. It processes
     /// events like window resize, expose/paint, focus changes, and close requests.
     ///
+    /// On Linux this also drives the host-side
+    /// [`IRunLoop`](super::runloop): the file descriptors and timers the
+    /// plugin's editor registered are polled and their handlers fired, which is
+    /// what lets JUCE/VSTGUI editors actually repaint and respond.
+    ///
+    /// When a plugin resizes itself through `IPlugFrame` during the pump the
+    /// new size is available from [`take_resize`](Vst3Gui::take_resize), so
+    /// event-loop consumers that did not register a
+    /// [`set_resize_callback`](Vst3Gui::set_resize_callback) can still react.
+    ///
     /// # Returns
     ///
     /// The number of events processed.
@@ -262,7 +388,16 @@ impl Vst3Gui {
     /// # }
     /// ```
     pub fn pump_events(&mut self) -> i32 {
-        unsafe { ffi::rack_vst3_gui_pump_events(self.handle) }
+        let native = unsafe { ffi::rack_vst3_gui_pump_events(self.handle) };
+        #[cfg(target_os = "linux")]
+        {
+            if native < 0 {
+                return native;
+            }
+            return native + self.run_loop.pump();
+        }
+        #[cfg(not(target_os = "linux"))]
+        native
     }
 
     /// Get the native window ID
@@ -279,6 +414,57 @@ impl Vst3Gui {
     pub fn get_window_id(&self) -> u64 {
         unsafe { ffi::rack_vst3_gui_get_window_id(self.handle) as u64 }
     }
+
+    /// Register a callback fired whenever the plugin resizes its own view
+    ///
+    /// The callback runs from within [`pump_events`](Vst3Gui::pump_events) when
+    /// the plugin calls `IPlugFrame::resizeView`, after the host container has
+    /// already been resized, with the accepted `(width, height)` in pixels.
+    /// Registering a new callback replaces any previous one.
+    ///
+    /// # Thread Safety
+    ///
+    /// Must be called from the main thread.
+    pub fn set_resize_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(u32, u32) + 'static,
+    {
+        self.plug_frame.set_callback(Box::new(callback));
+    }
+
+    /// Take the size the plugin last requested through `IPlugFrame`, if any
+    ///
+    /// Returns `Some((width, height))` once per plugin-initiated resize and
+    /// `None` otherwise, clearing the pending value. Use this to drive a host
+    /// layout from the event loop instead of (or in addition to) a
+    /// [`set_resize_callback`](Vst3Gui::set_resize_callback).
+    ///
+    /// # Thread Safety
+    ///
+    /// Must be called from the main thread.
+    pub fn take_resize(&mut self) -> Option<(u32, u32)> {
+        self.plug_frame.take_pending()
+    }
+
+    /// Resize the editor at the host's request
+    ///
+    /// Resizes the native container window and calls `IPlugView::onSize` so the
+    /// plugin lays its editor out to the new `(width, height)`. This is the
+    /// host-driven counterpart to a plugin-initiated `resizeView`; use it when
+    /// the host, rather than the plugin, decides the editor should change size.
+    ///
+    /// # Thread Safety
+    ///
+    /// Must be called from the main thread.
+    pub fn request_resize(&mut self, width: u32, height: u32) -> Result<()> {
+        unsafe {
+            let result = ffi::rack_vst3_gui_resize_window(self.handle, width, height);
+            if result < 0 {
+                return Err(Error::Other("Plugin rejected the requested size".into()));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Vst3Gui {
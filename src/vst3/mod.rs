@@ -6,7 +6,23 @@ mod instance;
 #[cfg(any(target_os = "linux", target_os = "windows"))]
 mod gui;
 
+#[cfg(target_os = "linux")]
+mod runloop;
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+mod plugframe;
+
+#[cfg(target_os = "linux")]
+mod scan_cache;
+
+mod preset;
+
 pub use scanner::Vst3Scanner;
+
+#[cfg(target_os = "linux")]
+pub use scan_cache::{encode_probe_result, probe_child_path, ProbeMetadata, ScanCache};
+
+pub use preset::{PluginState, VstPreset};
 pub use instance::Vst3Plugin;
 
 #[cfg(any(target_os = "linux", target_os = "windows"))]
@@ -0,0 +1,188 @@
+//! Host-side `Steinberg::IPlugFrame` implementation
+//!
+//! `IPlugView::getSize` only reports a static rect, but VST3 editors routinely
+//! resize themselves — when the user expands a panel or switches a skin the
+//! view calls `IPlugFrame::resizeView(view, newSize)` and expects the host to
+//! resize the container window and then call `IPlugView::onSize` back with the
+//! accepted rect. Without a frame the view has nowhere to send that request and
+//! either refuses to resize or draws out of bounds.
+//!
+//! This module implements the frame in Rust. [`PlugFrame`] owns the host-side
+//! state (the GUI handle used to resize the native window, the size the plugin
+//! last asked for, and an optional user callback); the COM vtable in
+//! [`IPlugFrame`] bridges the Steinberg ABI back into it. On `resizeView` the
+//! thunk resizes the host container through the FFI layer — which reparents and
+//! calls `onSize` on the view — then records the new size so
+//! [`Vst3Gui::pump_events`](super::Vst3Gui::pump_events) can surface it and
+//! fires the registered callback.
+//!
+//! # Thread Safety
+//!
+//! Like the rest of the GUI layer, the frame must only be driven from the main
+//! thread; the interface pointers exchanged with the plugin are not `Send`.
+
+use super::ffi;
+use std::os::raw::c_void;
+
+/// `Steinberg::tresult` codes used by the frame vtable.
+const K_RESULT_OK: i32 = 0;
+const K_RESULT_FALSE: i32 = 1;
+const K_INVALID_ARGUMENT: i32 = 0x8007_0057_u32 as i32;
+
+/// `Steinberg::ViewRect` — the rectangle a `resizeView` request carries.
+#[repr(C)]
+struct ViewRect {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+/// Host-side frame state shared with the plugin's view.
+///
+/// Owns the GUI handle so `resizeView` can resize the native container, the
+/// last size the plugin requested (drained by the GUI's event pump), and the
+/// optional resize callback registered through
+/// [`Vst3Gui::set_resize_callback`](super::Vst3Gui::set_resize_callback).
+pub(crate) struct PlugFrame {
+    handle: *mut ffi::RackVST3Gui,
+    pending_size: Option<(u32, u32)>,
+    callback: Option<Box<dyn FnMut(u32, u32)>>,
+}
+
+impl PlugFrame {
+    pub(crate) fn new(handle: *mut ffi::RackVST3Gui) -> Self {
+        Self {
+            handle,
+            pending_size: None,
+            callback: None,
+        }
+    }
+
+    /// Install the callback fired whenever the plugin resizes its own view.
+    pub(crate) fn set_callback(&mut self, callback: Box<dyn FnMut(u32, u32)>) {
+        self.callback = Some(callback);
+    }
+
+    /// Record a size change and notify the callback.
+    ///
+    /// Called after the native container has already been resized so the
+    /// callback and the value drained by `pump_events` reflect the rect the
+    /// view actually accepted.
+    pub(crate) fn record_resize(&mut self, width: u32, height: u32) {
+        self.pending_size = Some((width, height));
+        if let Some(callback) = self.callback.as_mut() {
+            callback(width, height);
+        }
+    }
+
+    /// Take the size the plugin last requested, if any, clearing it.
+    pub(crate) fn take_pending(&mut self) -> Option<(u32, u32)> {
+        self.pending_size.take()
+    }
+}
+
+/// COM-compatible `IPlugFrame` the host hands to the plugin's `IPlugView`.
+///
+/// The vtable thunks downcast the `this` pointer to an [`IPlugFrame`] and
+/// forward into the owned [`PlugFrame`]. The pointer is stable for the lifetime
+/// of the owning GUI, which outlives the view.
+#[repr(C)]
+pub(crate) struct IPlugFrame {
+    vtbl: *const IPlugFrameVtbl,
+    frame: *mut PlugFrame,
+}
+
+#[repr(C)]
+struct IPlugFrameVtbl {
+    query_interface: unsafe extern "C" fn(*mut c_void, *const u8, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "C" fn(*mut c_void) -> u32,
+    release: unsafe extern "C" fn(*mut c_void) -> u32,
+    resize_view: unsafe extern "C" fn(*mut IPlugFrame, *mut c_void, *const ViewRect) -> i32,
+}
+
+static IPLUGFRAME_VTBL: IPlugFrameVtbl = IPlugFrameVtbl {
+    query_interface: plugframe_query_interface,
+    add_ref: plugframe_add_ref,
+    release: plugframe_release,
+    resize_view: plugframe_resize_view,
+};
+
+// The frame is owned by the GUI, not reference counted, so FUnknown is a no-op
+// stub: queryInterface only ever returns the frame itself.
+unsafe extern "C" fn plugframe_query_interface(
+    this: *mut c_void,
+    _iid: *const u8,
+    obj: *mut *mut c_void,
+) -> i32 {
+    if obj.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    *obj = this;
+    K_RESULT_OK
+}
+
+unsafe extern "C" fn plugframe_add_ref(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "C" fn plugframe_release(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "C" fn plugframe_resize_view(
+    this: *mut IPlugFrame,
+    _view: *mut c_void,
+    new_size: *const ViewRect,
+) -> i32 {
+    if this.is_null() || new_size.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    let rect = &*new_size;
+    let width = (rect.right - rect.left).max(0) as u32;
+    let height = (rect.bottom - rect.top).max(0) as u32;
+
+    let frame = &mut *(*this).frame;
+
+    // Resize the host container; the FFI layer reparents the window and calls
+    // IPlugView::onSize back with the accepted rect. Refuse the request if the
+    // host could not satisfy it, matching what plugins expect on kResultFalse.
+    let rc = unsafe { ffi::rack_vst3_gui_resize_window(frame.handle, width, height) };
+    if rc < 0 {
+        return K_RESULT_FALSE;
+    }
+
+    frame.record_resize(width, height);
+    K_RESULT_OK
+}
+
+impl IPlugFrame {
+    /// Build a COM `IPlugFrame` bound to `frame`. The caller must keep both the
+    /// returned box and `frame` alive for as long as the plugin holds the
+    /// pointer.
+    pub(crate) fn new(frame: *mut PlugFrame) -> Box<Self> {
+        Box::new(Self {
+            vtbl: &IPLUGFRAME_VTBL,
+            frame,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_resize_stores_pending_and_fires_callback() {
+        let mut frame = PlugFrame::new(std::ptr::null_mut());
+        let seen = std::rc::Rc::new(std::cell::Cell::new(None));
+        let sink = seen.clone();
+        frame.set_callback(Box::new(move |w, h| sink.set(Some((w, h)))));
+
+        frame.record_resize(640, 480);
+        assert_eq!(seen.get(), Some((640, 480)));
+        assert_eq!(frame.take_pending(), Some((640, 480)));
+        // Draining clears it.
+        assert_eq!(frame.take_pending(), None);
+    }
+}
@@ -0,0 +1,266 @@
+//! Plugin state serialization and Steinberg `.vstpreset` support
+//!
+//! A host needs to persist and recall a plugin's state for session save and
+//! preset management. [`Vst3Plugin::get_state`](super::Vst3Plugin::get_state)
+//! drives `IComponent::getState` and the controller's `getState` into an opaque
+//! blob (and `set_state` the reverse), using the component-then-controller
+//! ordering real hosts use. This module owns the two serialization concerns
+//! that are independent of the COM plumbing:
+//!
+//! * [`PluginState`] — the in-memory split of component and controller state,
+//!   plus the opaque blob framing returned by `get_state` / accepted by
+//!   `set_state`.
+//! * [`VstPreset`] — reading and writing the on-disk `.vstpreset` container: a
+//!   header carrying the class FUID, the raw component/controller state, and a
+//!   trailing chunk list referencing each chunk by offset.
+
+use crate::{Error, Result};
+use std::path::Path;
+
+/// Component and controller state, kept separate so they can be applied in the
+/// component-then-controller order hosts require.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginState {
+    pub component: Vec<u8>,
+    pub controller: Vec<u8>,
+}
+
+/// Magic prefixing the opaque `get_state`/`set_state` blob (`'RKST'`).
+const STATE_BLOB_MAGIC: u32 = 0x524B_5354;
+
+impl PluginState {
+    /// Serialize to the opaque blob returned by `get_state`. The format is
+    /// `magic`, then each of the component and controller chunks length-prefixed
+    /// with a little-endian `u32`, so `set_state` can split them back apart
+    /// without ambiguity.
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(12 + self.component.len() + self.controller.len());
+        buf.extend_from_slice(&STATE_BLOB_MAGIC.to_le_bytes());
+        buf.extend_from_slice(&(self.component.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&self.component);
+        buf.extend_from_slice(&(self.controller.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&self.controller);
+        buf
+    }
+
+    /// Parse an opaque blob produced by [`to_blob`](PluginState::to_blob).
+    pub fn from_blob(buf: &[u8]) -> Result<Self> {
+        let read_u32 = |b: &[u8], at: usize| -> Result<u32> {
+            b.get(at..at + 4)
+                .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+                .ok_or_else(|| Error::Other("Truncated state blob".to_string()))
+        };
+
+        if read_u32(buf, 0)? != STATE_BLOB_MAGIC {
+            return Err(Error::Other("Unrecognized state blob".to_string()));
+        }
+
+        let comp_len = read_u32(buf, 4)? as usize;
+        let comp_start = 8;
+        let comp_end = comp_start + comp_len;
+        let component = buf
+            .get(comp_start..comp_end)
+            .ok_or_else(|| Error::Other("Truncated component state".to_string()))?
+            .to_vec();
+
+        let ctrl_len = read_u32(buf, comp_end)? as usize;
+        let ctrl_start = comp_end + 4;
+        let ctrl_end = ctrl_start + ctrl_len;
+        let controller = buf
+            .get(ctrl_start..ctrl_end)
+            .ok_or_else(|| Error::Other("Truncated controller state".to_string()))?
+            .to_vec();
+
+        Ok(Self {
+            component,
+            controller,
+        })
+    }
+}
+
+/// A parsed `.vstpreset` file: the plugin class FUID plus its component and
+/// controller state chunks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VstPreset {
+    /// The plugin's class id as a 32-character FUID string.
+    pub class_id: String,
+    pub state: PluginState,
+}
+
+// Steinberg .vstpreset container constants.
+const PRESET_HEADER_ID: &[u8; 4] = b"VST3";
+const PRESET_VERSION: i32 = 1;
+const CLASS_ID_SIZE: usize = 32;
+/// `"VST3"` + version(i32) + classID(32) + chunkListOffset(i64).
+const PRESET_HEADER_SIZE: i64 = 4 + 4 + CLASS_ID_SIZE as i64 + 8;
+const CHUNK_LIST_ID: &[u8; 4] = b"List";
+const CHUNK_ID_COMPONENT: &[u8; 4] = b"Comp";
+const CHUNK_ID_CONTROLLER: &[u8; 4] = b"Cont";
+
+impl VstPreset {
+    /// Build a preset from a plugin class id and its captured state.
+    pub fn new(class_id: impl Into<String>, state: PluginState) -> Self {
+        Self {
+            class_id: class_id.into(),
+            state,
+        }
+    }
+
+    /// Serialize to the `.vstpreset` binary layout.
+    pub fn encode(&self) -> Vec<u8> {
+        let comp_offset = PRESET_HEADER_SIZE;
+        let cont_offset = comp_offset + self.state.component.len() as i64;
+        let chunk_list_offset = cont_offset + self.state.controller.len() as i64;
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(PRESET_HEADER_ID);
+        buf.extend_from_slice(&PRESET_VERSION.to_le_bytes());
+        buf.extend_from_slice(&class_id_bytes(&self.class_id));
+        buf.extend_from_slice(&chunk_list_offset.to_le_bytes());
+
+        // Raw state chunks referenced by the list below.
+        buf.extend_from_slice(&self.state.component);
+        buf.extend_from_slice(&self.state.controller);
+
+        // Trailing chunk list.
+        buf.extend_from_slice(CHUNK_LIST_ID);
+        buf.extend_from_slice(&2i32.to_le_bytes()); // entry count
+        write_chunk_entry(
+            &mut buf,
+            CHUNK_ID_COMPONENT,
+            comp_offset,
+            self.state.component.len() as i64,
+        );
+        write_chunk_entry(
+            &mut buf,
+            CHUNK_ID_CONTROLLER,
+            cont_offset,
+            self.state.controller.len() as i64,
+        );
+        buf
+    }
+
+    /// Parse a `.vstpreset` binary blob.
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf.len() < PRESET_HEADER_SIZE as usize {
+            return Err(Error::Other("Preset file too small".to_string()));
+        }
+        if &buf[0..4] != PRESET_HEADER_ID {
+            return Err(Error::Other("Not a VST3 preset file".to_string()));
+        }
+
+        let class_id = String::from_utf8_lossy(&buf[8..8 + CLASS_ID_SIZE])
+            .trim_end_matches('\0')
+            .to_string();
+        let chunk_list_offset = read_i64(buf, 8 + CLASS_ID_SIZE)? as usize;
+
+        if buf.get(chunk_list_offset..chunk_list_offset + 4) != Some(CHUNK_LIST_ID.as_slice()) {
+            return Err(Error::Other("Missing preset chunk list".to_string()));
+        }
+        let entry_count = read_i32(buf, chunk_list_offset + 4)?;
+
+        let mut state = PluginState::default();
+        let mut pos = chunk_list_offset + 8;
+        for _ in 0..entry_count {
+            let id = buf
+                .get(pos..pos + 4)
+                .ok_or_else(|| Error::Other("Truncated chunk entry".to_string()))?;
+            let offset = read_i64(buf, pos + 4)? as usize;
+            let size = read_i64(buf, pos + 12)? as usize;
+            let chunk = buf
+                .get(offset..offset + size)
+                .ok_or_else(|| Error::Other("Chunk extends past preset".to_string()))?
+                .to_vec();
+            if id == CHUNK_ID_COMPONENT {
+                state.component = chunk;
+            } else if id == CHUNK_ID_CONTROLLER {
+                state.controller = chunk;
+            }
+            pos += 4 + 8 + 8;
+        }
+
+        Ok(Self { class_id, state })
+    }
+
+    /// Write the preset to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.encode())
+            .map_err(|e| Error::Other(format!("Failed to write preset {}: {}", path.display(), e)))
+    }
+
+    /// Read a preset from `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| Error::Other(format!("Failed to read preset {}: {}", path.display(), e)))?;
+        Self::decode(&bytes)
+    }
+}
+
+fn class_id_bytes(class_id: &str) -> [u8; CLASS_ID_SIZE] {
+    let mut out = [0u8; CLASS_ID_SIZE];
+    let bytes = class_id.as_bytes();
+    let len = bytes.len().min(CLASS_ID_SIZE);
+    out[..len].copy_from_slice(&bytes[..len]);
+    out
+}
+
+fn write_chunk_entry(buf: &mut Vec<u8>, id: &[u8; 4], offset: i64, size: i64) {
+    buf.extend_from_slice(id);
+    buf.extend_from_slice(&offset.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+}
+
+fn read_i32(buf: &[u8], at: usize) -> Result<i32> {
+    buf.get(at..at + 4)
+        .map(|s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+        .ok_or_else(|| Error::Other("Truncated preset field".to_string()))
+}
+
+fn read_i64(buf: &[u8], at: usize) -> Result<i64> {
+    buf.get(at..at + 8)
+        .map(|s| i64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]))
+        .ok_or_else(|| Error::Other("Truncated preset field".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_state() -> PluginState {
+        PluginState {
+            component: vec![1, 2, 3, 4, 5],
+            controller: vec![9, 8, 7],
+        }
+    }
+
+    #[test]
+    fn state_blob_roundtrips() {
+        let state = sample_state();
+        let blob = state.to_blob();
+        assert_eq!(PluginState::from_blob(&blob).unwrap(), state);
+    }
+
+    #[test]
+    fn empty_controller_state_roundtrips() {
+        let state = PluginState {
+            component: vec![0xAB; 16],
+            controller: Vec::new(),
+        };
+        assert_eq!(PluginState::from_blob(&state.to_blob()).unwrap(), state);
+    }
+
+    #[test]
+    fn preset_roundtrips() {
+        let preset = VstPreset::new("565354416E6F74686572506C7567696E", sample_state());
+        let bytes = preset.encode();
+        let decoded = VstPreset::decode(&bytes).unwrap();
+        assert_eq!(decoded, preset);
+    }
+
+    #[test]
+    fn rejects_non_preset_blob() {
+        let mut bytes = VstPreset::new("X", sample_state()).encode();
+        bytes[0] = b'X';
+        assert!(VstPreset::decode(&bytes).is_err());
+    }
+}
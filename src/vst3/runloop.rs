@@ -0,0 +1,333 @@
+//! Host-side `Steinberg::Linux::IRunLoop` implementation
+//!
+//! Modern VST3 editors on Linux (JUCE / VSTGUI based) do not run their own
+//! event loop. When the plugin's `IPlugView` receives its frame via
+//! `setFrame`, it `queryInterface`s the frame for `IRunLoop` and hands the
+//! host the file descriptors it needs watched (its X11 connection, pipes, …)
+//! through `registerEventHandler(handler, fd)` and periodic callbacks through
+//! `registerTimer(handler, interval_ms)`. Without a host run loop these
+//! editors never repaint and appear blank or frozen.
+//!
+//! This module implements the run loop itself in Rust: it owns the set of
+//! registered `(fd, IEventHandler*)` and `(interval, ITimerHandler*, deadline)`
+//! entries, and [`RunLoop::pump`] `poll()`s the descriptors with a timeout
+//! equal to the nearest timer deadline, dispatching `onFDIsSet` for each ready
+//! fd and `onTimer` for each elapsed timer. The COM vtable in [`IRunLoop`]
+//! bridges the Steinberg ABI back into these methods so the view can register
+//! and unregister handlers.
+//!
+//! # Thread Safety
+//!
+//! Like the rest of the GUI layer, the run loop must only be driven from the
+//! main thread; the raw interface pointers handed over by the plugin are not
+//! `Send`.
+
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
+
+/// `Steinberg::tresult` success / failure codes used by the run loop vtable.
+const K_RESULT_OK: i32 = 0;
+const K_INVALID_ARGUMENT: i32 = 0x8007_0057_u32 as i32;
+
+/// `Steinberg::Linux::IEventHandler` — the plugin object notified when a
+/// registered file descriptor becomes ready.
+#[repr(C)]
+pub(crate) struct IEventHandler {
+    vtbl: *const IEventHandlerVtbl,
+}
+
+#[repr(C)]
+struct IEventHandlerVtbl {
+    query_interface: unsafe extern "C" fn(*mut c_void, *const u8, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "C" fn(*mut c_void) -> u32,
+    release: unsafe extern "C" fn(*mut c_void) -> u32,
+    on_fd_is_set: unsafe extern "C" fn(*mut IEventHandler, fd: c_int),
+}
+
+/// `Steinberg::Linux::ITimerHandler` — the plugin object notified on each
+/// elapsed timer interval.
+#[repr(C)]
+pub(crate) struct ITimerHandler {
+    vtbl: *const ITimerHandlerVtbl,
+}
+
+#[repr(C)]
+struct ITimerHandlerVtbl {
+    query_interface: unsafe extern "C" fn(*mut c_void, *const u8, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "C" fn(*mut c_void) -> u32,
+    release: unsafe extern "C" fn(*mut c_void) -> u32,
+    on_timer: unsafe extern "C" fn(*mut ITimerHandler),
+}
+
+struct EventHandlerEntry {
+    fd: RawFd,
+    handler: *mut IEventHandler,
+}
+
+struct TimerEntry {
+    interval: Duration,
+    handler: *mut ITimerHandler,
+    next: Instant,
+}
+
+/// The host run loop: owns the registered descriptors and timers and drives
+/// them from [`pump`](RunLoop::pump).
+pub(crate) struct RunLoop {
+    event_handlers: Vec<EventHandlerEntry>,
+    timers: Vec<TimerEntry>,
+}
+
+impl RunLoop {
+    pub(crate) fn new() -> Self {
+        Self {
+            event_handlers: Vec::new(),
+            timers: Vec::new(),
+        }
+    }
+
+    /// Register a file descriptor to watch. Duplicate `(fd, handler)` pairs are
+    /// ignored so a view that re-registers after a reparent does not leak slots.
+    pub(crate) fn register_event_handler(&mut self, handler: *mut IEventHandler, fd: RawFd) {
+        if handler.is_null() {
+            return;
+        }
+        if self
+            .event_handlers
+            .iter()
+            .any(|e| e.fd == fd && e.handler == handler)
+        {
+            return;
+        }
+        self.event_handlers.push(EventHandlerEntry { fd, handler });
+    }
+
+    /// Drop every entry matching `handler`.
+    pub(crate) fn unregister_event_handler(&mut self, handler: *mut IEventHandler) {
+        self.event_handlers.retain(|e| e.handler != handler);
+    }
+
+    /// Register a periodic timer. A `milliseconds` of 0 is clamped to 1ms to
+    /// avoid a busy spin, matching what hosts do for zero-interval timers.
+    pub(crate) fn register_timer(&mut self, handler: *mut ITimerHandler, milliseconds: u64) {
+        if handler.is_null() {
+            return;
+        }
+        let interval = Duration::from_millis(milliseconds.max(1));
+        self.timers.push(TimerEntry {
+            interval,
+            handler,
+            next: Instant::now() + interval,
+        });
+    }
+
+    /// Drop every timer matching `handler`.
+    pub(crate) fn unregister_timer(&mut self, handler: *mut ITimerHandler) {
+        self.timers.retain(|t| t.handler != handler);
+    }
+
+    /// Poll the registered descriptors once and fire any elapsed timers.
+    ///
+    /// The `poll()` timeout is the time remaining until the nearest timer
+    /// deadline (0 when a timer is already due, or when no timers are
+    /// registered, so the call stays non-blocking for the GUI pump loop).
+    /// Returns the number of handler callbacks dispatched.
+    pub(crate) fn pump(&mut self) -> i32 {
+        let now = Instant::now();
+
+        // Timeout = remaining time until the soonest timer deadline.
+        let timeout_ms: c_int = match self.timers.iter().map(|t| t.next).min() {
+            Some(next) if next > now => (next - now).as_millis().min(c_int::MAX as u128) as c_int,
+            _ => 0,
+        };
+
+        let mut dispatched = 0;
+
+        if !self.event_handlers.is_empty() {
+            let mut pollfds: Vec<libc::pollfd> = self
+                .event_handlers
+                .iter()
+                .map(|e| libc::pollfd {
+                    fd: e.fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+
+            let rc =
+                unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout_ms) };
+
+            if rc > 0 {
+                for (pfd, entry) in pollfds.iter().zip(self.event_handlers.iter()) {
+                    if pfd.revents != 0 {
+                        // Safety: the plugin guarantees the handler outlives its
+                        // registration; we call onFDIsSet with the ready fd.
+                        unsafe {
+                            ((*(*entry.handler).vtbl).on_fd_is_set)(entry.handler, entry.fd);
+                        }
+                        dispatched += 1;
+                    }
+                }
+            }
+        } else if timeout_ms > 0 {
+            std::thread::sleep(Duration::from_millis(timeout_ms as u64));
+        }
+
+        // Fire every timer whose deadline has passed, advancing it so we catch
+        // up without drifting if a pump was late.
+        let now = Instant::now();
+        for timer in &mut self.timers {
+            if timer.next <= now {
+                // Safety: as above, the handler is owned by the live view.
+                unsafe {
+                    ((*(*timer.handler).vtbl).on_timer)(timer.handler);
+                }
+                dispatched += 1;
+                timer.next += timer.interval;
+                if timer.next <= now {
+                    timer.next = now + timer.interval;
+                }
+            }
+        }
+
+        dispatched
+    }
+}
+
+/// COM-compatible `IRunLoop` the host hands to the plugin's `IPlugView`.
+///
+/// The vtable thunks downcast the `this` pointer to an [`IRunLoop`] and forward
+/// into the owned [`RunLoop`]. The pointer is stable for the lifetime of the
+/// owning GUI, which outlives the view.
+#[repr(C)]
+pub(crate) struct IRunLoop {
+    vtbl: *const IRunLoopVtbl,
+    run_loop: *mut RunLoop,
+}
+
+#[repr(C)]
+struct IRunLoopVtbl {
+    query_interface: unsafe extern "C" fn(*mut c_void, *const u8, *mut *mut c_void) -> i32,
+    add_ref: unsafe extern "C" fn(*mut c_void) -> u32,
+    release: unsafe extern "C" fn(*mut c_void) -> u32,
+    register_event_handler:
+        unsafe extern "C" fn(*mut IRunLoop, *mut IEventHandler, c_int) -> i32,
+    unregister_event_handler: unsafe extern "C" fn(*mut IRunLoop, *mut IEventHandler) -> i32,
+    register_timer: unsafe extern "C" fn(*mut IRunLoop, *mut ITimerHandler, u64) -> i32,
+    unregister_timer: unsafe extern "C" fn(*mut IRunLoop, *mut ITimerHandler) -> i32,
+}
+
+static IRUNLOOP_VTBL: IRunLoopVtbl = IRunLoopVtbl {
+    query_interface: runloop_query_interface,
+    add_ref: runloop_add_ref,
+    release: runloop_release,
+    register_event_handler: runloop_register_event_handler,
+    unregister_event_handler: runloop_unregister_event_handler,
+    register_timer: runloop_register_timer,
+    unregister_timer: runloop_unregister_timer,
+};
+
+// The run loop is owned by the GUI, not reference counted, so FUnknown is a
+// no-op stub: queryInterface only ever returns the run loop itself.
+unsafe extern "C" fn runloop_query_interface(
+    this: *mut c_void,
+    _iid: *const u8,
+    obj: *mut *mut c_void,
+) -> i32 {
+    if obj.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    *obj = this;
+    K_RESULT_OK
+}
+
+unsafe extern "C" fn runloop_add_ref(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "C" fn runloop_release(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "C" fn runloop_register_event_handler(
+    this: *mut IRunLoop,
+    handler: *mut IEventHandler,
+    fd: c_int,
+) -> i32 {
+    if this.is_null() || handler.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    (*(*this).run_loop).register_event_handler(handler, fd);
+    K_RESULT_OK
+}
+
+unsafe extern "C" fn runloop_unregister_event_handler(
+    this: *mut IRunLoop,
+    handler: *mut IEventHandler,
+) -> i32 {
+    if this.is_null() || handler.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    (*(*this).run_loop).unregister_event_handler(handler);
+    K_RESULT_OK
+}
+
+unsafe extern "C" fn runloop_register_timer(
+    this: *mut IRunLoop,
+    handler: *mut ITimerHandler,
+    milliseconds: u64,
+) -> i32 {
+    if this.is_null() || handler.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    (*(*this).run_loop).register_timer(handler, milliseconds);
+    K_RESULT_OK
+}
+
+unsafe extern "C" fn runloop_unregister_timer(
+    this: *mut IRunLoop,
+    handler: *mut ITimerHandler,
+) -> i32 {
+    if this.is_null() || handler.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    (*(*this).run_loop).unregister_timer(handler);
+    K_RESULT_OK
+}
+
+impl IRunLoop {
+    /// Build a COM `IRunLoop` bound to `run_loop`. The caller must keep both the
+    /// returned box and `run_loop` alive for as long as the plugin holds the
+    /// pointer.
+    pub(crate) fn new(run_loop: *mut RunLoop) -> Box<Self> {
+        Box::new(Self {
+            vtbl: &IRUNLOOP_VTBL,
+            run_loop,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_and_unregister_event_handler() {
+        let mut rl = RunLoop::new();
+        let handler = 0x1 as *mut IEventHandler;
+        rl.register_event_handler(handler, 7);
+        // Duplicate registration is ignored.
+        rl.register_event_handler(handler, 7);
+        assert_eq!(rl.event_handlers.len(), 1);
+        rl.unregister_event_handler(handler);
+        assert!(rl.event_handlers.is_empty());
+    }
+
+    #[test]
+    fn zero_interval_timer_is_clamped() {
+        let mut rl = RunLoop::new();
+        rl.register_timer(0x2 as *mut ITimerHandler, 0);
+        assert_eq!(rl.timers.len(), 1);
+        assert_eq!(rl.timers[0].interval, Duration::from_millis(1));
+    }
+}
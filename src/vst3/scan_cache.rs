@@ -0,0 +1,487 @@
+//! Out-of-process plugin scanning with an on-disk cache and blacklist
+//!
+//! Loading and instantiating every plugin in-process means one plugin that
+//! segfaults or hangs during instantiation takes down the whole scan (and the
+//! host). Following the approach Ardour uses for its VST scanner, each plugin
+//! is probed in a short-lived child process that only opens the module, pulls
+//! out metadata, and serializes it back over a pipe. The parent enforces a
+//! per-plugin timeout; anything that times out, crashes, or errors is recorded
+//! in a persistent blacklist, and successful probes are cached keyed by
+//! `path + mtime + size` so subsequent scans only touch changed files.
+//!
+//! The cache and blacklist live in a small binary store under the platform
+//! cache directory. The serialization uses the same little-endian,
+//! length-delimited conventions as [`crate::wine_host::protocol`], so the
+//! child/parent wire format and the on-disk format share one encoding.
+
+use crate::{Error, PluginType, Result};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Current on-disk cache format version. Bumped whenever [`ProbeMetadata`] or
+/// the key derivation changes so stale stores are discarded rather than
+/// misparsed.
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// Magic identifying the cache store file (`'RKSC'`).
+const CACHE_MAGIC: u32 = 0x524B_5343;
+
+/// Default per-plugin probe timeout.
+const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Identity of a plugin file on disk. Two files with the same path, mtime, and
+/// size are treated as identical, so an unchanged plugin is served from cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub path: String,
+    pub mtime: i64,
+    pub size: u64,
+}
+
+impl CacheKey {
+    /// Derive the key from the file's metadata. Returns an error if the path
+    /// cannot be stat'd.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = std::fs::metadata(path)
+            .map_err(|e| Error::Other(format!("Failed to stat {}: {}", path.display(), e)))?;
+        Ok(Self {
+            path: path.display().to_string(),
+            mtime: meta.mtime(),
+            size: meta.size(),
+        })
+    }
+}
+
+/// Metadata extracted by a successful probe. This is the subset a host needs to
+/// list a plugin without instantiating it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeMetadata {
+    pub name: String,
+    pub manufacturer: String,
+    pub uid: String,
+    pub num_params: u32,
+    pub num_audio_inputs: u32,
+    pub num_audio_outputs: u32,
+    pub plugin_type: PluginType,
+}
+
+/// A persistent cache of probe results plus a blacklist of plugins that failed
+/// to probe.
+pub struct ScanCache {
+    store_path: PathBuf,
+    entries: Vec<(CacheKey, ProbeMetadata)>,
+    blacklist: Vec<CacheKey>,
+    probe_timeout: Duration,
+}
+
+impl ScanCache {
+    /// Open (or create) the cache store under the platform cache directory,
+    /// `$XDG_CACHE_HOME/rack/scan-cache` falling back to `$HOME/.cache/...`.
+    pub fn open() -> Result<Self> {
+        Self::open_at(Self::default_store_path()?)
+    }
+
+    /// Open a cache store at an explicit path instead of the default location.
+    pub fn open_at(store_path: PathBuf) -> Result<Self> {
+        let mut cache = Self {
+            store_path,
+            entries: Vec::new(),
+            blacklist: Vec::new(),
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+        };
+        if cache.store_path.exists() {
+            // A corrupt or stale-version store is treated as empty rather than
+            // fatal: a bad cache should never block scanning.
+            if let Ok(bytes) = std::fs::read(&cache.store_path) {
+                let _ = cache.decode(&bytes);
+            }
+        }
+        Ok(cache)
+    }
+
+    fn default_store_path() -> Result<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+            .ok_or_else(|| Error::Other("No HOME or XDG_CACHE_HOME set".to_string()))?;
+        Ok(base.join("rack").join("scan-cache"))
+    }
+
+    /// Override the per-plugin probe timeout.
+    pub fn with_probe_timeout(mut self, timeout: Duration) -> Self {
+        self.probe_timeout = timeout;
+        self
+    }
+
+    /// Return the cached metadata for `path` if it is present and unchanged.
+    pub fn lookup(&self, path: &Path) -> Option<&ProbeMetadata> {
+        let key = CacheKey::from_path(path).ok()?;
+        self.entries
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, meta)| meta)
+    }
+
+    /// Whether `path` (at its current mtime/size) is blacklisted.
+    pub fn is_blacklisted(&self, path: &Path) -> bool {
+        match CacheKey::from_path(path) {
+            Ok(key) => self.blacklist.contains(&key),
+            Err(_) => false,
+        }
+    }
+
+    /// Probe `path` in a child process, returning cached metadata when possible
+    /// and otherwise spawning a short-lived probe. A probe that times out,
+    /// crashes, or errors adds `path` to the blacklist and returns an error.
+    pub fn probe(&mut self, path: &Path) -> Result<ProbeMetadata> {
+        let key = CacheKey::from_path(path)?;
+
+        if let Some((_, meta)) = self.entries.iter().find(|(k, _)| *k == key) {
+            return Ok(meta.clone());
+        }
+        if self.blacklist.contains(&key) {
+            return Err(Error::Other(format!(
+                "Plugin is blacklisted (previously failed to scan): {}",
+                path.display()
+            )));
+        }
+
+        match probe_in_subprocess(path, self.probe_timeout) {
+            Ok(meta) => {
+                self.entries.push((key, meta.clone()));
+                Ok(meta)
+            }
+            Err(e) => {
+                self.blacklist.push(key);
+                Err(e)
+            }
+        }
+    }
+
+    /// Forget any cached result and blacklist entry for `path`, forcing the next
+    /// [`probe`](ScanCache::probe) to re-run a fresh subprocess.
+    pub fn rescan(&mut self, path: &Path) {
+        let path_str = path.display().to_string();
+        self.entries.retain(|(k, _)| k.path != path_str);
+        self.blacklist.retain(|k| k.path != path_str);
+    }
+
+    /// Drop every blacklist entry so previously failing plugins are retried.
+    pub fn clear_blacklist(&mut self) {
+        self.blacklist.clear();
+    }
+
+    /// Persist the cache and blacklist to disk, creating parent directories as
+    /// needed.
+    pub fn flush(&self) -> Result<()> {
+        if let Some(parent) = self.store_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::Other(format!("Failed to create cache dir: {}", e)))?;
+        }
+        std::fs::write(&self.store_path, self.encode())
+            .map_err(|e| Error::Other(format!("Failed to write scan cache: {}", e)))
+    }
+
+    // --- binary serialization (little-endian, length-delimited strings) ---
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&CACHE_MAGIC.to_le_bytes());
+        buf.extend_from_slice(&CACHE_FORMAT_VERSION.to_le_bytes());
+
+        buf.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (key, meta) in &self.entries {
+            encode_key(&mut buf, key);
+            encode_meta(&mut buf, meta);
+        }
+
+        buf.extend_from_slice(&(self.blacklist.len() as u32).to_le_bytes());
+        for key in &self.blacklist {
+            encode_key(&mut buf, key);
+        }
+        buf
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> Result<()> {
+        let mut r = Reader::new(buf);
+        if r.u32()? != CACHE_MAGIC || r.u32()? != CACHE_FORMAT_VERSION {
+            return Err(Error::Other("Unrecognized scan cache format".to_string()));
+        }
+
+        let num_entries = r.u32()?;
+        let mut entries = Vec::with_capacity(num_entries as usize);
+        for _ in 0..num_entries {
+            let key = decode_key(&mut r)?;
+            let meta = decode_meta(&mut r)?;
+            entries.push((key, meta));
+        }
+
+        let num_blacklist = r.u32()?;
+        let mut blacklist = Vec::with_capacity(num_blacklist as usize);
+        for _ in 0..num_blacklist {
+            blacklist.push(decode_key(&mut r)?);
+        }
+
+        self.entries = entries;
+        self.blacklist = blacklist;
+        Ok(())
+    }
+}
+
+fn encode_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn encode_key(buf: &mut Vec<u8>, key: &CacheKey) {
+    encode_string(buf, &key.path);
+    buf.extend_from_slice(&key.mtime.to_le_bytes());
+    buf.extend_from_slice(&key.size.to_le_bytes());
+}
+
+fn encode_meta(buf: &mut Vec<u8>, meta: &ProbeMetadata) {
+    encode_string(buf, &meta.name);
+    encode_string(buf, &meta.manufacturer);
+    encode_string(buf, &meta.uid);
+    buf.extend_from_slice(&meta.num_params.to_le_bytes());
+    buf.extend_from_slice(&meta.num_audio_inputs.to_le_bytes());
+    buf.extend_from_slice(&meta.num_audio_outputs.to_le_bytes());
+    buf.extend_from_slice(&plugin_type_to_u32(meta.plugin_type).to_le_bytes());
+}
+
+fn decode_key(r: &mut Reader) -> Result<CacheKey> {
+    Ok(CacheKey {
+        path: r.string()?,
+        mtime: r.i64()?,
+        size: r.u64()?,
+    })
+}
+
+fn decode_meta(r: &mut Reader) -> Result<ProbeMetadata> {
+    Ok(ProbeMetadata {
+        name: r.string()?,
+        manufacturer: r.string()?,
+        uid: r.string()?,
+        num_params: r.u32()?,
+        num_audio_inputs: r.u32()?,
+        num_audio_outputs: r.u32()?,
+        plugin_type: plugin_type_from_u32(r.u32()?),
+    })
+}
+
+fn plugin_type_to_u32(t: PluginType) -> u32 {
+    match t {
+        PluginType::Instrument => 1,
+        _ => 0,
+    }
+}
+
+fn plugin_type_from_u32(v: u32) -> PluginType {
+    match v {
+        1 => PluginType::Instrument,
+        _ => PluginType::Effect,
+    }
+}
+
+/// Minimal checked little-endian reader; every accessor errors on truncation
+/// rather than panicking, so a corrupt store degrades to "empty cache".
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.pos + n > self.buf.len() {
+            return Err(Error::Other("Truncated scan cache".to_string()));
+        }
+        let slice = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let b = self.take(8)?;
+        Ok(u64::from_le_bytes([
+            b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+        ]))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(self.u64()? as i64)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        Ok(String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+/// Probe a single plugin in a short-lived child process, enforcing `timeout`.
+///
+/// The child is this executable re-invoked in probe mode (selected via the
+/// `RACK_PROBE_PLUGIN` environment variable, which [`probe_child_path`] reads).
+/// It opens the module, extracts [`ProbeMetadata`], and writes the serialized
+/// result to stdout; the parent reads it with a watchdog and kills the child if
+/// it overruns the timeout.
+fn probe_in_subprocess(path: &Path, timeout: Duration) -> Result<ProbeMetadata> {
+    let exe = std::env::current_exe()
+        .map_err(|e| Error::Other(format!("Cannot locate current executable: {}", e)))?;
+
+    let mut child = Command::new(exe)
+        .env("RACK_PROBE_PLUGIN", path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| Error::Other(format!("Failed to spawn probe process: {}", e)))?;
+
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| Error::Other("Probe process has no stdout".to_string()))?;
+
+    // Read the child's output on a worker thread so the parent can enforce the
+    // timeout via the channel recv rather than blocking on the pipe.
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = stdout.read_to_end(&mut bytes);
+        let _ = tx.send(bytes);
+    });
+
+    let result = rx.recv_timeout(timeout);
+
+    // Regardless of outcome, reap the child so a crashed/hung probe cannot
+    // linger. A timeout is an explicit kill.
+    let status = match result {
+        Ok(_) => child.wait().ok(),
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            None
+        }
+    };
+
+    match result {
+        Err(_) => Err(Error::Other(format!(
+            "Probe timed out after {:?}: {}",
+            timeout,
+            path.display()
+        ))),
+        Ok(bytes) => {
+            if !status.map(|s| s.success()).unwrap_or(false) {
+                return Err(Error::Other(format!(
+                    "Probe process crashed or exited with error: {}",
+                    path.display()
+                )));
+            }
+            let mut r = Reader::new(&bytes);
+            decode_meta(&mut r).map_err(|_| {
+                Error::Other(format!("Probe returned malformed metadata: {}", path.display()))
+            })
+        }
+    }
+}
+
+/// The plugin path the current process should probe, if it was launched in
+/// probe mode. A binary built on top of this crate calls this early in `main`
+/// and, when it returns `Some`, performs the open/extract/serialize dance
+/// against that path and exits, instead of running normally.
+pub fn probe_child_path() -> Option<PathBuf> {
+    std::env::var_os("RACK_PROBE_PLUGIN").map(PathBuf::from)
+}
+
+/// Serialize probe metadata for the child to write to stdout. Pairs with the
+/// parent-side decode in [`probe_in_subprocess`].
+pub fn encode_probe_result(meta: &ProbeMetadata) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_meta(&mut buf, meta);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_meta() -> ProbeMetadata {
+        ProbeMetadata {
+            name: "Test Synth".to_string(),
+            manufacturer: "Acme".to_string(),
+            uid: "ABCD1234".to_string(),
+            num_params: 42,
+            num_audio_inputs: 0,
+            num_audio_outputs: 2,
+            plugin_type: PluginType::Instrument,
+        }
+    }
+
+    #[test]
+    fn probe_result_roundtrips() {
+        let meta = sample_meta();
+        let bytes = encode_probe_result(&meta);
+        let decoded = decode_meta(&mut Reader::new(&bytes)).unwrap();
+        assert_eq!(meta, decoded);
+    }
+
+    #[test]
+    fn store_roundtrips_entries_and_blacklist() {
+        let mut cache = ScanCache {
+            store_path: PathBuf::from("/does/not/matter"),
+            entries: vec![(
+                CacheKey {
+                    path: "/plugins/a.vst3".to_string(),
+                    mtime: 123,
+                    size: 456,
+                },
+                sample_meta(),
+            )],
+            blacklist: vec![CacheKey {
+                path: "/plugins/bad.vst3".to_string(),
+                mtime: 7,
+                size: 8,
+            }],
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+        };
+        let bytes = cache.encode();
+
+        let mut decoded = ScanCache {
+            store_path: PathBuf::from("/does/not/matter"),
+            entries: Vec::new(),
+            blacklist: Vec::new(),
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+        };
+        decoded.decode(&bytes).unwrap();
+        assert_eq!(decoded.entries, cache.entries);
+        assert_eq!(decoded.blacklist, cache.blacklist);
+
+        // clear_blacklist empties only the blacklist.
+        cache.clear_blacklist();
+        assert!(cache.blacklist.is_empty());
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn truncated_store_is_rejected_not_panicked() {
+        let mut cache = ScanCache {
+            store_path: PathBuf::from("/x"),
+            entries: Vec::new(),
+            blacklist: Vec::new(),
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+        };
+        assert!(cache.decode(&[0x53, 0x43]).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+//! Length-delimited framing and request/response dispatch for the Wine host IPC
+//!
+//! The transport frames every message as `[Header][payload]` on a single TCP
+//! stream. [`RpcClient`] owns a background reader thread that pulls complete
+//! frames off the socket and routes each [`ResponseHeader`] back to its caller
+//! by `request_id` through a pending map — the same client/server model the
+//! `audioipc` crate uses. This lets callers pipeline many in-flight commands
+//! (e.g. enumerating `GetParamInfo`) instead of blocking one-at-a-time, and a
+//! response for a command whose caller already gave up is simply dropped when
+//! its id is no longer in the map.
+//!
+//! When the client is created for the legacy v1 protocol (no `request_id` on
+//! the wire), the reader matches responses against in-flight requests in FIFO
+//! order. Requests are framed with the current [`Header`] layout in either
+//! mode.
+
+use super::protocol::{
+    Header, HostCommand, ResponseHeader, Status, RACK_WINE_PROTOCOL_VERSION_V1,
+    RACK_WINE_RESPONSE_MAGIC,
+};
+use crate::{Error, Result};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use zerocopy::{AsBytes, FromBytes};
+
+/// A decoded response: the host status and the payload bytes.
+type Response = (Status, Vec<u8>);
+
+/// Shared routing table from `request_id` to the waiting caller's channel.
+///
+/// For v1 hosts that echo no id, `order` records the ids in send order so the
+/// reader can match the next response to the oldest outstanding request.
+#[derive(Default)]
+struct Pending {
+    waiters: HashMap<u32, mpsc::Sender<Response>>,
+    order: VecDeque<u32>,
+}
+
+/// Pipelined RPC client over the Wine host TCP stream.
+pub(super) struct RpcClient {
+    stream: Mutex<TcpStream>,
+    pending: Arc<Mutex<Pending>>,
+    next_id: AtomicU32,
+    reader: Option<JoinHandle<()>>,
+}
+
+/// A handle to a command already written to the wire; await it to get the reply.
+pub(super) struct PendingResponse {
+    rx: Receiver<Response>,
+    id: u32,
+    pending: Arc<Mutex<Pending>>,
+}
+
+/// How long a caller waits for a response before giving up, matching the socket
+/// read timeout the lock-step transport used before pipelining.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl RpcClient {
+    /// Wrap a connected stream, negotiating `version`, and spawn the reader.
+    ///
+    /// `version` is the protocol the peer speaks; v1 selects the FIFO fallback.
+    pub(super) fn new(stream: TcpStream, version: u32) -> Result<Self> {
+        let read_half = stream
+            .try_clone()
+            .map_err(|e| Error::Other(format!("Failed to clone Wine host stream: {}", e)))?;
+        // The reader blocks indefinitely between frames; the caller-side timeout
+        // lives on `PendingResponse::wait`, not on the socket, so an idle stream
+        // must not trip the connect-time read timeout and kill the reader.
+        read_half
+            .set_read_timeout(None)
+            .map_err(|e| Error::Other(format!("Failed to clear reader timeout: {}", e)))?;
+        let pending = Arc::new(Mutex::new(Pending::default()));
+
+        let reader_pending = Arc::clone(&pending);
+        let reader = std::thread::Builder::new()
+            .name("rack-wine-rpc".to_string())
+            .spawn(move || reader_loop(read_half, version, reader_pending))
+            .map_err(|e| Error::Other(format!("Failed to spawn RPC reader: {}", e)))?;
+
+        Ok(Self {
+            stream: Mutex::new(stream),
+            pending,
+            next_id: AtomicU32::new(1),
+            reader: Some(reader),
+        })
+    }
+
+    /// Frame a command onto the wire and return a handle for its response.
+    ///
+    /// Returns immediately without waiting, so several commands can be in flight
+    /// at once; collect them by awaiting each [`PendingResponse`].
+    pub(super) fn send(&self, cmd: HostCommand, payload: &[u8], instance_id: u32) -> Result<PendingResponse> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.waiters.insert(id, tx);
+            pending.order.push_back(id);
+        }
+
+        let header = Header::new(cmd, payload.len() as u32, id, instance_id);
+        let mut stream = self.stream.lock().unwrap();
+        if let Err(e) = stream
+            .write_all(header.as_bytes())
+            .and_then(|_| {
+                if payload.is_empty() {
+                    Ok(())
+                } else {
+                    stream.write_all(payload)
+                }
+            })
+        {
+            // Undo the registration so a failed send does not leak a waiter.
+            let mut pending = self.pending.lock().unwrap();
+            pending.waiters.remove(&id);
+            pending.order.retain(|&pid| pid != id);
+            return Err(Error::Other(format!("Failed to send command: {}", e)));
+        }
+
+        Ok(PendingResponse {
+            rx,
+            id,
+            pending: Arc::clone(&self.pending),
+        })
+    }
+
+    /// Send a command and block until its response arrives, checking status.
+    pub(super) fn call(&self, cmd: HostCommand, payload: &[u8], instance_id: u32) -> Result<Vec<u8>> {
+        self.send(cmd, payload, instance_id)?.wait()
+    }
+
+    /// Write a batch of commands to the wire up front, then collect their
+    /// replies in order.
+    ///
+    /// All frames are pipelined before any response is awaited, so a batch of
+    /// `N` commands (e.g. enumerating every parameter) costs one round trip
+    /// rather than `N`. Each entry's result is reported independently; a failed
+    /// send or errored response becomes an `Err` in that slot without aborting
+    /// the rest.
+    pub(super) fn request_many(&self, batch: &[(HostCommand, Vec<u8>)], instance_id: u32) -> Vec<Result<Vec<u8>>> {
+        let pending: Vec<_> = batch
+            .iter()
+            .map(|(cmd, payload)| self.send(*cmd, payload, instance_id))
+            .collect();
+        pending
+            .into_iter()
+            .map(|p| p.and_then(|p| p.wait()))
+            .collect()
+    }
+}
+
+impl PendingResponse {
+    /// Block until the matching response arrives and map its status to a result.
+    pub(super) fn wait(self) -> Result<Vec<u8>> {
+        match self.rx.recv_timeout(REQUEST_TIMEOUT) {
+            Ok((status, payload)) => match status {
+                Status::Ok => Ok(payload),
+                Status::NotLoaded | Status::NotInitialized => Err(Error::NotInitialized),
+                Status::InvalidParam => Err(Error::InvalidParameter(0)),
+                Status::Error => Err(Error::Other("Wine host returned error".to_string())),
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(Error::Other("Wine host request timed out".to_string()))
+            }
+            // The reader thread cleared the waiters: the connection is gone.
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(Error::Other("Wine host connection closed".to_string()))
+            }
+        }
+    }
+
+}
+
+impl Drop for PendingResponse {
+    fn drop(&mut self) {
+        // A caller that gives up (e.g. on timeout) deregisters itself so a late
+        // response is discarded rather than mismatched to a future request.
+        let mut pending = self.pending.lock().unwrap();
+        pending.waiters.remove(&self.id);
+        pending.order.retain(|&pid| pid != self.id);
+    }
+}
+
+impl Drop for RpcClient {
+    fn drop(&mut self) {
+        // Shutting the stream down unblocks the reader's `read_exact`, which then
+        // returns and lets the thread exit.
+        if let Ok(stream) = self.stream.lock() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Route decoded frames to their waiters until the stream closes.
+fn reader_loop(mut stream: TcpStream, version: u32, pending: Arc<Mutex<Pending>>) {
+    let v1 = version <= RACK_WINE_PROTOCOL_VERSION_V1;
+    loop {
+        match read_frame(&mut stream, v1) {
+            Ok((request_id, status, payload)) => {
+                // On v1 the id is always 0, so fall back to the oldest request.
+                let mut guard = pending.lock().unwrap();
+                let target = if v1 {
+                    guard.order.front().copied()
+                } else {
+                    Some(request_id)
+                };
+                if let Some(id) = target {
+                    guard.order.retain(|&pid| pid != id);
+                    if let Some(tx) = guard.waiters.remove(&id) {
+                        let _ = tx.send((status, payload));
+                    }
+                }
+                // An unknown id (caller already gave up) is simply dropped.
+            }
+            // EOF or any read/framing error terminates the reader.
+            Err(_) => break,
+        }
+    }
+
+    // Drop every outstanding sender so callers blocked in `wait` observe the
+    // disconnect instead of waiting out the full request timeout.
+    let mut guard = pending.lock().unwrap();
+    guard.waiters.clear();
+    guard.order.clear();
+}
+
+/// Read one `[ResponseHeader][payload]` frame, returning `(id, status, payload)`.
+fn read_frame(stream: &mut TcpStream, v1: bool) -> Result<(u32, Status, Vec<u8>)> {
+    if v1 {
+        // Legacy header: magic, status, payload_size (no request_id).
+        let mut buf = [0u8; 12];
+        stream
+            .read_exact(&mut buf)
+            .map_err(|e| Error::Other(format!("Failed to read response header: {}", e)))?;
+        let magic = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        if magic != RACK_WINE_RESPONSE_MAGIC {
+            return Err(Error::Other("Invalid response magic".to_string()));
+        }
+        let status = Status::from(u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]));
+        let payload_size = u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) as usize;
+        let payload = read_payload(stream, payload_size)?;
+        Ok((0, status, payload))
+    } else {
+        let mut buf = [0u8; std::mem::size_of::<ResponseHeader>()];
+        stream
+            .read_exact(&mut buf)
+            .map_err(|e| Error::Other(format!("Failed to read response header: {}", e)))?;
+        let header = ResponseHeader::read_from(&buf[..])
+            .ok_or_else(|| Error::Other("Short response header".to_string()))?;
+        if header.magic.get() != RACK_WINE_RESPONSE_MAGIC {
+            return Err(Error::Other("Invalid response magic".to_string()));
+        }
+        let payload = read_payload(stream, header.payload_size.get() as usize)?;
+        Ok((header.request_id.get(), header.status(), payload))
+    }
+}
+
+fn read_payload(stream: &mut TcpStream, size: usize) -> Result<Vec<u8>> {
+    let mut payload = vec![0u8; size];
+    if size > 0 {
+        stream
+            .read_exact(&mut payload)
+            .map_err(|e| Error::Other(format!("Failed to read response payload: {}", e)))?;
+    }
+    Ok(payload)
+}
@@ -0,0 +1,111 @@
+//! Sealed `memfd` creation and `SCM_RIGHTS` descriptor passing (Linux only)
+//!
+//! The named-shm path ([`CmdInitAudio`](super::protocol::CmdInitAudio)) looks
+//! the audio buffer up by a string that races on collisions and leaks a
+//! `/tmp` file if the host crashes. This module provides the alternative the
+//! `audioipc` crate uses: the client creates the buffer with `memfd_create`,
+//! seals it so neither side can resize it, and passes the descriptor to the
+//! host as ancillary data over an `AF_UNIX` control socket. The host `mmap`s
+//! the received fd directly, there is no guessable name, and the kernel frees
+//! the buffer as soon as every reference — including the host's — is closed.
+
+use crate::{Error, Result};
+use std::ffi::CString;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+
+/// Create an anonymous shared buffer of `size` bytes and seal it.
+///
+/// The returned descriptor is sealed against growing, shrinking, and further
+/// sealing, so the size both processes `mmap` is fixed for its lifetime. The
+/// caller owns the fd and is responsible for closing it once it has been
+/// `mmap`ed and handed to the host.
+pub(super) fn create_sealed_memfd(name: &str, size: usize) -> Result<RawFd> {
+    let cname = CString::new(name)
+        .map_err(|_| Error::Other("memfd name contains a NUL byte".to_string()))?;
+
+    // Safety: `cname` is a valid NUL-terminated string for the duration of the
+    // call; memfd_create returns an owned fd or -1.
+    let fd = unsafe {
+        libc::memfd_create(
+            cname.as_ptr(),
+            (libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC) as libc::c_uint,
+        )
+    };
+    if fd < 0 {
+        return Err(Error::Other(format!(
+            "memfd_create failed: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    if unsafe { libc::ftruncate(fd, size as libc::off_t) } < 0 {
+        let err = std::io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(Error::Other(format!("ftruncate of memfd failed: {}", err)));
+    }
+
+    let seals = libc::F_SEAL_SEAL | libc::F_SEAL_GROW | libc::F_SEAL_SHRINK;
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+        let err = std::io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(Error::Other(format!("sealing memfd failed: {}", err)));
+    }
+
+    Ok(fd)
+}
+
+/// Send `payload` and a single descriptor `fd` over a connected `AF_UNIX`
+/// socket using an `SCM_RIGHTS` control message.
+pub(super) fn send_fd(socket: &UnixStream, payload: &[u8], fd: RawFd) -> Result<()> {
+    // A control buffer large enough for one fd; CMSG_SPACE(4) is 24 bytes on
+    // 64-bit Linux, so 32 is comfortably sufficient.
+    let mut cmsg_buf = [0u8; 32];
+
+    let mut iov = libc::iovec {
+        iov_base: payload.as_ptr() as *mut libc::c_void,
+        iov_len: payload.len(),
+    };
+
+    // Safety: msghdr is zeroed then filled with pointers that outlive the call.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) } as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null() {
+            return Err(Error::Other("failed to build SCM_RIGHTS header".to_string()));
+        }
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        std::ptr::copy_nonoverlapping(
+            &fd as *const RawFd as *const u8,
+            libc::CMSG_DATA(cmsg),
+            mem::size_of::<RawFd>(),
+        );
+
+        let sent = libc::sendmsg(socket.as_raw_fd(), &msg, 0);
+        if sent < 0 {
+            return Err(Error::Other(format!(
+                "sendmsg with SCM_RIGHTS failed: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        // The fd rides the first datagram, so a short write would leave the host
+        // with a truncated header and no way to recover the rest in band.
+        if sent as usize != payload.len() {
+            return Err(Error::Other(format!(
+                "sendmsg wrote {} of {} control bytes",
+                sent,
+                payload.len()
+            )));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Seqlock reader over the offscreen editor framebuffer (Linux only)
+//!
+//! The offscreen editor mode hands the Wine host a client-allocated RGBA buffer
+//! prefixed by an [`FbHeader`]. The host's GUI thread is the producer: it bumps
+//! `sequence` to odd, paints the dirtied rectangle, then stores an even
+//! `sequence`. A compositor that wants a tear-free frame reads `sequence` first,
+//! blits, and re-reads it — if it is unchanged and even, the pixels it copied
+//! belong to a single published frame. This is the same acquire/release
+//! sequence-counter discipline the audio [`ring`](super::ring) uses, applied to
+//! a single mailbox slot rather than an SPSC queue.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::protocol::FbHeader;
+
+/// A damage rectangle in framebuffer pixels, in the coordinate space of the
+/// most recently published frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamageRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A view over the framebuffer embedded in the mapped editor buffer.
+///
+/// The consumer (the caller compositing the GUI) only ever reads; the Wine host
+/// is the sole producer. The view borrows nothing beyond the raw mapping, which
+/// the owning [`EditorSurface`](super::EditorSurface) keeps alive.
+pub(super) struct ShmFramebuffer {
+    base: *mut u8,
+    /// Bytes from `base` to the first pixel, i.e. `FbHeader::SIZE`.
+    pixel_offset: usize,
+    stride: usize,
+    height: usize,
+}
+
+impl ShmFramebuffer {
+    /// Wrap a mapped framebuffer whose header geometry has been initialised.
+    ///
+    /// # Safety
+    /// `base` must point to a live mapping of at least
+    /// `FbHeader::SIZE + stride * height` bytes that outlives the view.
+    pub(super) unsafe fn new(base: *mut u8, stride: u32, height: u32) -> Self {
+        Self {
+            base,
+            pixel_offset: FbHeader::SIZE,
+            stride: stride as usize,
+            height: height as usize,
+        }
+    }
+
+    fn header(&self) -> &FbHeader {
+        // Safety: the mapping starts with a valid FbHeader (see `new`).
+        unsafe { &*(self.base as *const FbHeader) }
+    }
+
+    fn sequence(&self) -> &AtomicU32 {
+        // Safety: the sequence field is a 32-bit word inside the live mapping;
+        // on the little-endian x86_64 targets this host runs on its bytes alias
+        // an AtomicU32.
+        unsafe { &*(std::ptr::addr_of!(self.header().sequence) as *const AtomicU32) }
+    }
+
+    /// Number of bytes in the pixel region (`stride * height`).
+    pub(super) fn pixels_len(&self) -> usize {
+        self.stride * self.height
+    }
+
+    /// Raw pointer to the first pixel, for callers that blit in place and accept
+    /// the small tearing risk of not snapshotting under the seqlock.
+    pub(super) fn pixels_ptr(&self) -> *const u8 {
+        // Safety: the pixel region follows the header within the mapping.
+        unsafe { self.base.add(self.pixel_offset) }
+    }
+
+    /// Copy the most recently published frame into `dst`, returning the frame's
+    /// sequence number and damage rectangle, or `None` if `dst` is too small.
+    ///
+    /// The copy is retried until the seqlock reports a stable even sequence, so
+    /// the bytes in `dst` never mix two frames. `dst` must hold at least
+    /// [`pixels_len`](Self::pixels_len) bytes.
+    pub(super) fn read_frame(&self, dst: &mut [u8]) -> Option<(u32, DamageRect)> {
+        let len = self.pixels_len();
+        if dst.len() < len {
+            return None;
+        }
+        loop {
+            let before = self.sequence().load(Ordering::Acquire);
+            if before & 1 != 0 {
+                // Producer mid-write; spin for the publish.
+                std::hint::spin_loop();
+                continue;
+            }
+            // Safety: the pixel region is `len` bytes within the live mapping.
+            unsafe {
+                std::ptr::copy_nonoverlapping(self.pixels_ptr(), dst.as_mut_ptr(), len);
+            }
+            let damage = DamageRect {
+                x: self.header().damage_x.get(),
+                y: self.header().damage_y.get(),
+                width: self.header().damage_w.get(),
+                height: self.header().damage_h.get(),
+            };
+            // Re-read: an unchanged even sequence means the copy is consistent.
+            if self.sequence().load(Ordering::Acquire) == before {
+                return Some((before, damage));
+            }
+        }
+    }
+}
@@ -4,85 +4,69 @@
 //! It spawns a Wine host process that loads the plugin and communicates
 //! via TCP socket and shared memory for audio.
 
+mod codec;
+#[cfg(target_os = "linux")]
+mod fd_passing;
+#[cfg(target_os = "linux")]
+mod framebuffer;
 mod protocol;
+#[cfg(target_os = "linux")]
+mod ring;
+mod state_codec;
 
 use crate::{Error, MidiEvent, ParameterInfo, PluginInfo, PluginInstance, PluginScanner, PluginType, PresetInfo, Result};
+use codec::RpcClient;
 use protocol::*;
+use zerocopy::byteorder::U32;
+use zerocopy::AsBytes;
+
+#[cfg(target_os = "linux")]
+use std::os::unix::net::UnixStream;
+#[cfg(target_os = "linux")]
+use zerocopy::FromBytes;
+
+/// Path of the host's `AF_UNIX` fd-passing control socket for a given TCP port.
+#[cfg(target_os = "linux")]
+fn control_socket_path(port: u16) -> String {
+    format!("/tmp/rack-wine-ctl-{}.sock", port)
+}
 
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
 use std::net::TcpStream;
 use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 /// Path to the Wine host executable (relative to the crate or absolute)
 const WINE_HOST_EXE: &str = "rack-wine-host.exe";
 
-/// Global counter for unique shared memory names
-static SHM_COUNTER: AtomicU32 = AtomicU32::new(0);
-
-/// IPC client for communicating with the Wine host
+/// IPC client for a single plugin instance on a (possibly shared) Wine host
+///
+/// Commands are framed and dispatched through the server's [`RpcClient`], so
+/// enumeration and other batchable queries can be pipelined rather than sent
+/// lock-step. Many clients share one [`RpcClient`] — each tags its commands
+/// with a distinct `instance_id` so the multiplexed host routes them to the
+/// right plugin instance (see [`WineHostServer`]).
 struct WineClient {
-    stream: TcpStream,
+    rpc: Arc<RpcClient>,
+    /// Instance this client addresses on the shared host; 0 for a single-instance host.
+    instance_id: u32,
+    /// Control socket used to hand the audio buffer to the host as a descriptor.
+    ///
+    /// Present only when the host is listening on its `AF_UNIX` control path;
+    /// [`init_audio_fd`](WineClient::init_audio_fd) needs it to send the memfd
+    /// with `SCM_RIGHTS`. `None` falls back to the named-shm path.
+    #[cfg(target_os = "linux")]
+    control: Option<UnixStream>,
 }
 
 impl WineClient {
-    /// Connect to Wine host on given port
-    fn connect(port: u16) -> Result<Self> {
-        let addr = format!("127.0.0.1:{}", port);
-        let stream = TcpStream::connect(&addr)
-            .map_err(|e| Error::Other(format!("Failed to connect to Wine host: {}", e)))?;
-        stream.set_read_timeout(Some(Duration::from_secs(30)))
-            .map_err(|e| Error::Other(format!("Failed to set read timeout: {}", e)))?;
-        stream.set_write_timeout(Some(Duration::from_secs(30)))
-            .map_err(|e| Error::Other(format!("Failed to set write timeout: {}", e)))?;
-        Ok(Self { stream })
-    }
-
-    /// Send a command with optional payload
-    fn send_command(&mut self, cmd: HostCommand, payload: &[u8]) -> Result<()> {
-        let header = Header::new(cmd, payload.len() as u32);
-        self.stream.write_all(&header.to_bytes())
-            .map_err(|e| Error::Other(format!("Failed to send header: {}", e)))?;
-        if !payload.is_empty() {
-            self.stream.write_all(payload)
-                .map_err(|e| Error::Other(format!("Failed to send payload: {}", e)))?;
-        }
-        Ok(())
-    }
-
-    /// Receive a response
-    fn recv_response(&mut self) -> Result<(ResponseHeader, Vec<u8>)> {
-        let mut header_buf = [0u8; 12];
-        self.stream.read_exact(&mut header_buf)
-            .map_err(|e| Error::Other(format!("Failed to read response header: {}", e)))?;
-
-        let header = ResponseHeader::from_bytes(&header_buf);
-        if header.magic != RACK_WINE_RESPONSE_MAGIC {
-            return Err(Error::Other("Invalid response magic".to_string()));
-        }
-
-        let mut payload = vec![0u8; header.payload_size as usize];
-        if header.payload_size > 0 {
-            self.stream.read_exact(&mut payload)
-                .map_err(|e| Error::Other(format!("Failed to read response payload: {}", e)))?;
-        }
-
-        Ok((header, payload))
-    }
-
     /// Send command and receive response, checking status
     fn request(&mut self, cmd: HostCommand, payload: &[u8]) -> Result<Vec<u8>> {
-        self.send_command(cmd, payload)?;
-        let (header, payload) = self.recv_response()?;
-        match header.status() {
-            Status::Ok => Ok(payload),
-            Status::NotLoaded => Err(Error::NotInitialized),
-            Status::NotInitialized => Err(Error::NotInitialized),
-            Status::InvalidParam => Err(Error::InvalidParameter(0)),
-            Status::Error => Err(Error::Other("Wine host returned error".to_string())),
-        }
+        self.rpc.call(cmd, payload, self.instance_id)
     }
 
     /// Ping the host
@@ -94,7 +78,7 @@ impl WineClient {
     /// Load a plugin
     fn load_plugin(&mut self, path: &str, class_index: u32) -> Result<()> {
         let cmd = CmdLoadPlugin::new(path, class_index);
-        self.request(HostCommand::LoadPlugin, &cmd.to_bytes())?;
+        self.request(HostCommand::LoadPlugin, cmd.as_bytes())?;
         Ok(())
     }
 
@@ -122,10 +106,28 @@ impl WineClient {
             .ok_or_else(|| Error::Other("Invalid param info response".to_string()))
     }
 
+    /// Fetch info for every parameter, pipelining the requests
+    ///
+    /// All `GetParamInfo` commands are written to the wire up front and their
+    /// responses collected afterwards, so enumeration costs one round trip
+    /// rather than one per parameter. Parameters whose response is missing or
+    /// malformed are skipped, matching the previous per-index loop.
+    fn get_all_param_info(&mut self, count: u32) -> Vec<RespParamInfo> {
+        let batch: Vec<_> = (0..count)
+            .map(|i| (HostCommand::GetParamInfo, i.to_le_bytes().to_vec()))
+            .collect();
+        self.rpc
+            .request_many(&batch, self.instance_id)
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .filter_map(|payload| RespParamInfo::from_bytes(&payload))
+            .collect()
+    }
+
     /// Get parameter value
     fn get_param(&mut self, param_id: u32) -> Result<f64> {
         let cmd = CmdParam::new(param_id, 0.0);
-        let payload = self.request(HostCommand::GetParam, &cmd.to_bytes())?;
+        let payload = self.request(HostCommand::GetParam, cmd.as_bytes())?;
         if payload.len() >= 8 {
             Ok(f64::from_le_bytes([
                 payload[0], payload[1], payload[2], payload[3],
@@ -139,32 +141,113 @@ impl WineClient {
     /// Set parameter value
     fn set_param(&mut self, param_id: u32, value: f64) -> Result<()> {
         let cmd = CmdParam::new(param_id, value);
-        self.request(HostCommand::SetParam, &cmd.to_bytes())?;
+        self.request(HostCommand::SetParam, cmd.as_bytes())?;
+        Ok(())
+    }
+
+    /// Fetch the plugin's opaque state chunk for the given [`ChunkScope`].
+    ///
+    /// The host frames its reply with a [`state_codec`] descriptor; the caller
+    /// decodes it. Read-only, so it borrows `&self` like the underlying call.
+    fn get_chunk(&self, scope: ChunkScope) -> Result<Vec<u8>> {
+        let cmd = CmdChunkScope::new(scope);
+        self.rpc.call(HostCommand::GetState, cmd.as_bytes(), self.instance_id)
+    }
+
+    /// Push an opaque state chunk to the plugin under the given [`ChunkScope`].
+    ///
+    /// The scope word prefixes the encoded container so the host restores it
+    /// into the bank or just the current program.
+    fn set_chunk(&self, scope: ChunkScope, blob: &[u8]) -> Result<()> {
+        let scope_word = CmdChunkScope::new(scope);
+        let mut payload = Vec::with_capacity(CmdChunkScope::SIZE + blob.len());
+        payload.extend_from_slice(scope_word.as_bytes());
+        payload.extend_from_slice(blob);
+        self.rpc.call(HostCommand::SetState, &payload, self.instance_id)?;
         Ok(())
     }
 
-    /// Initialize audio
-    fn init_audio(&mut self, sample_rate: u32, block_size: u32, num_inputs: u32, num_outputs: u32, shm_name: &str) -> Result<()> {
-        let cmd = CmdInitAudio::new(sample_rate, block_size, num_inputs, num_outputs, shm_name);
-        self.request(HostCommand::InitAudio, &cmd.to_bytes())?;
+    /// Number of programs the plugin exposes.
+    fn preset_count(&self) -> Result<usize> {
+        let payload = self.rpc.call(HostCommand::GetPresetCount, &[], self.instance_id)?;
+        RespPresetCount::read_from_prefix(&payload)
+            .map(|r| r.count.get() as usize)
+            .ok_or_else(|| Error::Other("Invalid preset count response".to_string()))
+    }
+
+    /// Name and metadata for the program at `index`.
+    fn preset_info(&self, index: usize) -> Result<RespPresetInfo> {
+        let cmd = CmdPresetInfo::new(index as u32);
+        let payload = self.rpc.call(HostCommand::GetPresetInfo, cmd.as_bytes(), self.instance_id)?;
+        RespPresetInfo::from_bytes(&payload)
+            .ok_or_else(|| Error::Other("Invalid preset info response".to_string()))
+    }
+
+    /// Select the plugin's current program.
+    fn load_preset(&self, preset_number: i32) -> Result<()> {
+        let cmd = CmdLoadPreset::new(preset_number);
+        self.rpc.call(HostCommand::LoadPreset, cmd.as_bytes(), self.instance_id)?;
         Ok(())
     }
 
+    /// Initialize audio by passing the buffer descriptor over the control socket
+    ///
+    /// Sends [`CmdInitAudioFd`] framed as `[Header][payload]` together with `fd`
+    /// as `SCM_RIGHTS` ancillary data, then reads the host's acknowledgement off
+    /// the same socket. Requires a connected control socket (see
+    /// [`control`](WineClient::control)).
+    #[cfg(target_os = "linux")]
+    fn init_audio_fd(&mut self, sample_rate: u32, block_size: u32, num_inputs: u32, num_outputs: u32, fd: std::os::unix::io::RawFd) -> Result<()> {
+        use std::io::Read;
+
+        let control = self
+            .control
+            .as_mut()
+            .ok_or_else(|| Error::Other("Host does not expose an fd-passing socket".to_string()))?;
+
+        let cmd = CmdInitAudioFd::new(sample_rate, block_size, num_inputs, num_outputs);
+        let header = Header::new(
+            HostCommand::InitAudioFd,
+            cmd.as_bytes().len() as u32,
+            0,
+            self.instance_id,
+        );
+
+        let mut payload = Vec::with_capacity(header.as_bytes().len() + cmd.as_bytes().len());
+        payload.extend_from_slice(header.as_bytes());
+        payload.extend_from_slice(cmd.as_bytes());
+
+        fd_passing::send_fd(control, &payload, fd)?;
+
+        // The control socket is lock-step, so read the single response inline.
+        let mut resp_buf = [0u8; std::mem::size_of::<ResponseHeader>()];
+        control
+            .read_exact(&mut resp_buf)
+            .map_err(|e| Error::Other(format!("Failed to read fd-init response: {}", e)))?;
+        let resp = ResponseHeader::read_from(&resp_buf[..])
+            .ok_or_else(|| Error::Other("Short fd-init response".to_string()))?;
+        match resp.status() {
+            Status::Ok => Ok(()),
+            _ => Err(Error::Other("Host rejected fd-based audio init".to_string())),
+        }
+    }
+
     /// Process audio
     fn process_audio(&mut self, num_samples: u32) -> Result<()> {
         let cmd = CmdProcessAudio::new(num_samples);
-        self.request(HostCommand::ProcessAudio, &cmd.to_bytes())?;
+        self.request(HostCommand::ProcessAudio, cmd.as_bytes())?;
         Ok(())
     }
 
     /// Send MIDI events
-    fn send_midi(&mut self, events: &[protocol::MidiEvent]) -> Result<()> {
-        let mut payload = Vec::with_capacity(4 + events.len() * 8);
-        let header = CmdMidi { num_events: events.len() as u32 };
-        payload.extend_from_slice(&header.to_bytes());
-        for event in events {
-            payload.extend_from_slice(&event.to_bytes());
-        }
+    ///
+    /// Each event is framed as a [`MidiEventHeader`] plus its raw payload, so a
+    /// SysEx dump travels as its full `F0 … F7` slice while channel-voice
+    /// messages stay a compact three bytes. With `running_status` the batch is
+    /// running-status compressed (see [`encode_midi_batch`]); only pass it when
+    /// the host advertises [`RACK_WINE_CAP_MIDI_RUNNING_STATUS`].
+    fn send_midi(&mut self, events: &[protocol::MidiEventBytes], running_status: bool) -> Result<()> {
+        let payload = protocol::encode_midi_batch(events, running_status);
         self.request(HostCommand::SendMidi, &payload)?;
         Ok(())
     }
@@ -176,12 +259,60 @@ impl WineClient {
             .ok_or_else(|| Error::Other("Invalid editor info response".to_string()))
     }
 
+    /// Query the editor's preferred size without opening a window.
+    fn get_editor_size(&mut self) -> Result<RespEditorInfo> {
+        let payload = self.request(HostCommand::GetEditorSize, &[])?;
+        RespEditorInfo::from_bytes(&payload)
+            .ok_or_else(|| Error::Other("Invalid editor size response".to_string()))
+    }
+
     /// Close editor
     fn close_editor(&mut self) -> Result<()> {
         self.request(HostCommand::CloseEditor, &[])?;
         Ok(())
     }
 
+    /// Open the editor in offscreen mode, handing the host the client-allocated
+    /// framebuffer as a descriptor over the control socket.
+    ///
+    /// Frames `OpenEditorOffscreen` the same way [`init_audio_fd`](Self::init_audio_fd)
+    /// frames the audio buffer: `[Header][payload]` plus `fd` as `SCM_RIGHTS`
+    /// ancillary data, read lock-step off the control socket.
+    #[cfg(target_os = "linux")]
+    fn open_editor_offscreen_fd(&mut self, width: u32, height: u32, stride: u32, fd: std::os::unix::io::RawFd) -> Result<()> {
+        use std::io::Read;
+
+        let control = self
+            .control
+            .as_mut()
+            .ok_or_else(|| Error::Other("Host does not expose an fd-passing socket".to_string()))?;
+
+        let cmd = CmdOpenEditorOffscreen::new(width, height, stride);
+        let header = Header::new(
+            HostCommand::OpenEditorOffscreen,
+            cmd.as_bytes().len() as u32,
+            0,
+            self.instance_id,
+        );
+
+        let mut payload = Vec::with_capacity(header.as_bytes().len() + cmd.as_bytes().len());
+        payload.extend_from_slice(header.as_bytes());
+        payload.extend_from_slice(cmd.as_bytes());
+
+        fd_passing::send_fd(control, &payload, fd)?;
+
+        let mut resp_buf = [0u8; std::mem::size_of::<ResponseHeader>()];
+        control
+            .read_exact(&mut resp_buf)
+            .map_err(|e| Error::Other(format!("Failed to read offscreen-editor response: {}", e)))?;
+        let resp = ResponseHeader::read_from(&resp_buf[..])
+            .ok_or_else(|| Error::Other("Short offscreen-editor response".to_string()))?;
+        match resp.status() {
+            Status::Ok => Ok(()),
+            _ => Err(Error::Other("Host rejected offscreen editor".to_string())),
+        }
+    }
+
     /// Get parameter changes from GUI
     fn get_param_changes(&mut self) -> Result<Vec<protocol::ParamChangeEvent>> {
         let payload = self.request(HostCommand::GetParamChanges, &[])?;
@@ -207,6 +338,12 @@ impl WineClient {
         Ok(changes)
     }
 
+    /// Unload this instance's plugin, leaving the shared host running for others.
+    fn unload_plugin(&mut self) -> Result<()> {
+        self.request(HostCommand::UnloadPlugin, &[])?;
+        Ok(())
+    }
+
     /// Shutdown the host
     fn shutdown(&mut self) -> Result<()> {
         self.request(HostCommand::Shutdown, &[])?;
@@ -214,12 +351,236 @@ impl WineClient {
     }
 }
 
+/// Connect the shared command transport to the Wine host on `port`.
+fn connect_rpc(port: u16) -> Result<RpcClient> {
+    let addr = format!("127.0.0.1:{}", port);
+    let stream = TcpStream::connect(&addr)
+        .map_err(|e| Error::Other(format!("Failed to connect to Wine host: {}", e)))?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .map_err(|e| Error::Other(format!("Failed to set read timeout: {}", e)))?;
+    stream
+        .set_write_timeout(Some(Duration::from_secs(30)))
+        .map_err(|e| Error::Other(format!("Failed to set write timeout: {}", e)))?;
+    RpcClient::new(stream, RACK_WINE_PROTOCOL_VERSION)
+}
+
+/// Raise this process's open-file-descriptor soft limit toward the hard limit.
+///
+/// A shared [`WineHostServer`] holds one command/control socket and shm mapping
+/// per instance, so the default soft `RLIMIT_NOFILE` is easy to exhaust once a
+/// project loads dozens of plugins. Modelled on rustc's test-harness
+/// `raise_fd_limit`: best-effort, and any failure is ignored since it only ever
+/// loosens a limit.
+#[cfg(unix)]
+fn raise_fd_limit() {
+    // Safety: plain `getrlimit`/`setrlimit` syscalls over a local `rlimit`.
+    unsafe {
+        let mut rlim: libc::rlimit = std::mem::zeroed();
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim) != 0 {
+            return;
+        }
+        if rlim.rlim_cur < rlim.rlim_max {
+            rlim.rlim_cur = rlim.rlim_max;
+            let _ = libc::setrlimit(libc::RLIMIT_NOFILE, &rlim);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn raise_fd_limit() {}
+
+/// Continuously copy a child's stderr into `sink`, keeping only a bounded tail.
+///
+/// Runs on its own thread for the host's lifetime so the pipe never fills and
+/// stalls the host; the retained tail is what a crash report surfaces.
+fn drain_stderr(stderr: std::process::ChildStderr, sink: Arc<Mutex<String>>) {
+    /// Cap so a chatty host cannot grow the buffer without bound.
+    const MAX_TAIL: usize = 64 * 1024;
+    let mut reader = BufReader::new(stderr);
+    let mut line = String::new();
+    while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
+        if let Ok(mut buf) = sink.lock() {
+            buf.push_str(&line);
+            if buf.len() > MAX_TAIL {
+                let cut = buf.len() - MAX_TAIL;
+                buf.drain(..cut);
+            }
+        }
+        line.clear();
+    }
+}
+
+/// A Wine host process shared by many plugin instances.
+///
+/// Following audioipc2's one-server/many-streams model, a single `wine` process
+/// hosts every plugin a project loads: the [`RpcClient`] connection is shared,
+/// and each [`WineVst3Plugin`] is a lightweight handle addressing its own
+/// `instance_id`. The process is spawned once and torn down only when the last
+/// handle drops its `Arc`.
+struct WineHostServer {
+    /// The `wine` child, reaped when the server drops.
+    host_process: Mutex<Child>,
+    /// Multiplexed command connection shared by every [`WineClient`].
+    rpc: Arc<RpcClient>,
+    /// TCP port the host accepted on, used to reach its control socket.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    port: u16,
+    /// Hands out a fresh `instance_id` per loaded plugin.
+    next_instance: AtomicU32,
+    /// Host executable and prefix, retained so a crashed host can be respawned
+    /// with the identical command line (see [`WineVst3Plugin::health`]).
+    host_exe: PathBuf,
+    wine_prefix: Option<PathBuf>,
+    /// Rolling capture of the host's stderr, drained by a background thread so
+    /// the pipe never blocks the host and the tail is available after a crash.
+    stderr_log: Arc<Mutex<String>>,
+}
+
+impl WineHostServer {
+    /// Spawn one Wine host and connect the shared command transport.
+    ///
+    /// Rather than sleeping a fixed delay and probing the whole port range, the
+    /// host announces its bound port and a `READY` token on stdout; this parses
+    /// those and connects the instant the host is listening. A host that exits
+    /// before readiness surfaces its captured stderr in the error.
+    fn spawn(host_exe: &Path, wine_prefix: Option<&Path>) -> Result<Arc<Self>> {
+        // One server now holds many sockets and shm mappings at once, so lift
+        // the descriptor ceiling before we start handing out instances.
+        raise_fd_limit();
+
+        let mut cmd = Command::new("wine");
+        cmd.arg(host_exe);
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        if let Some(prefix) = wine_prefix {
+            cmd.env("WINEPREFIX", prefix);
+        }
+        let mut host_process = cmd
+            .spawn()
+            .map_err(|e| Error::Other(format!("Failed to spawn Wine host: {}", e)))?;
+
+        // Drain stderr into a rolling buffer off a background thread.
+        let stderr_log = Arc::new(Mutex::new(String::new()));
+        if let Some(stderr) = host_process.stderr.take() {
+            let sink = Arc::clone(&stderr_log);
+            std::thread::spawn(move || drain_stderr(stderr, sink));
+        }
+
+        // Read the startup banner: a `RACK_WINE_PORT <n>` line followed by the
+        // `READY` token. EOF here means the host died during startup.
+        let stdout = host_process
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Other("Wine host stdout not piped".to_string()))?;
+        let mut reader = BufReader::new(stdout);
+        let mut port = None;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let n = reader
+                .read_line(&mut line)
+                .map_err(|e| Error::Other(format!("Failed to read Wine host banner: {}", e)))?;
+            if n == 0 {
+                let tail = stderr_log.lock().map(|s| s.clone()).unwrap_or_default();
+                return Err(Error::Other(format!(
+                    "Wine host exited before becoming ready: {}",
+                    tail.trim()
+                )));
+            }
+            let trimmed = line.trim();
+            if let Some(rest) = trimmed.strip_prefix(RACK_WINE_PORT_PREFIX) {
+                port = rest.trim().parse::<u16>().ok();
+            } else if trimmed == RACK_WINE_READY_TOKEN {
+                break;
+            }
+        }
+        let port = port
+            .ok_or_else(|| Error::Other("Wine host did not announce a port".to_string()))?;
+
+        // Keep the stdout pipe drained so post-banner logging never blocks the host.
+        std::thread::spawn(move || {
+            let mut sink = reader;
+            let mut buf = String::new();
+            while sink.read_line(&mut buf).map(|n| n > 0).unwrap_or(false) {
+                buf.clear();
+            }
+        });
+
+        let rpc = connect_rpc(port)?;
+        Ok(Arc::new(Self {
+            host_process: Mutex::new(host_process),
+            rpc: Arc::new(rpc),
+            port,
+            next_instance: AtomicU32::new(0),
+            host_exe: host_exe.to_path_buf(),
+            wine_prefix: wine_prefix.map(|p| p.to_path_buf()),
+            stderr_log,
+        }))
+    }
+
+    /// Whether the host process is still running (has not exited).
+    fn is_alive(&self) -> bool {
+        match self.host_process.lock() {
+            // `try_wait` returning `Ok(None)` means the child is still alive.
+            Ok(mut child) => matches!(child.try_wait(), Ok(None)),
+            Err(_) => false,
+        }
+    }
+
+    /// Snapshot of the host's captured stderr, for crash diagnostics.
+    fn stderr_tail(&self) -> String {
+        self.stderr_log
+            .lock()
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Open a client for a fresh plugin instance on this host.
+    fn new_client(&self) -> WineClient {
+        let instance_id = self.next_instance.fetch_add(1, Ordering::Relaxed);
+
+        // Each instance opens its own control connection; SCM_RIGHTS fd passing
+        // is lock-step and must not race other instances on a shared socket.
+        #[cfg(target_os = "linux")]
+        let control = UnixStream::connect(control_socket_path(self.port))
+            .ok()
+            .inspect(|s| {
+                let _ = s.set_read_timeout(Some(Duration::from_secs(30)));
+                let _ = s.set_write_timeout(Some(Duration::from_secs(30)));
+            });
+
+        WineClient {
+            rpc: Arc::clone(&self.rpc),
+            instance_id,
+            #[cfg(target_os = "linux")]
+            control,
+        }
+    }
+}
+
+impl Drop for WineHostServer {
+    fn drop(&mut self) {
+        // Last handle gone: ask the host to exit, then reap it so the `wine`
+        // process does not linger.
+        let _ = self.rpc.call(HostCommand::Shutdown, &[], 0);
+        if let Ok(mut child) = self.host_process.lock() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
 /// Scanner for Windows VST3 plugins via Wine
 pub struct WineVst3Scanner {
     /// Path to Wine host executable
     host_exe: PathBuf,
     /// Optional Wine prefix
     wine_prefix: Option<PathBuf>,
+    /// The shared host, spawned lazily on the first [`load`](WineVst3Scanner::load)
+    /// and reused for every subsequent instance so a project of many plugins
+    /// runs under one `wine` process.
+    server: Mutex<Option<Arc<WineHostServer>>>,
 }
 
 impl WineVst3Scanner {
@@ -231,6 +592,7 @@ impl WineVst3Scanner {
         Self {
             host_exe: host_exe.as_ref().to_path_buf(),
             wine_prefix: None,
+            server: Mutex::new(None),
         }
     }
 
@@ -257,33 +619,15 @@ impl WineVst3Scanner {
         }
     }
 
-    /// Spawn Wine host and connect
-    fn spawn_host(&self) -> Result<(Child, WineClient)> {
-        // Build wine command
-        let mut cmd = Command::new("wine");
-        cmd.arg(&self.host_exe);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        if let Some(ref prefix) = self.wine_prefix {
-            cmd.env("WINEPREFIX", prefix);
-        }
-
-        // Spawn the host
-        let child = cmd.spawn()
-            .map_err(|e| Error::Other(format!("Failed to spawn Wine host: {}", e)))?;
-
-        // Wait a bit for the host to start
-        std::thread::sleep(Duration::from_millis(2000));
-
-        // Try to connect to ports in range
-        for port in RACK_WINE_PORT_BASE..=RACK_WINE_PORT_MAX {
-            if let Ok(client) = WineClient::connect(port) {
-                return Ok((child, client));
-            }
+    /// Return the shared host, spawning it on first use.
+    fn shared_server(&self) -> Result<Arc<WineHostServer>> {
+        let mut guard = self.server.lock().unwrap();
+        if let Some(server) = guard.as_ref() {
+            return Ok(Arc::clone(server));
         }
-
-        Err(Error::Other("Failed to connect to Wine host".to_string()))
+        let server = WineHostServer::spawn(&self.host_exe, self.wine_prefix.as_deref())?;
+        *guard = Some(Arc::clone(&server));
+        Ok(server)
     }
 }
 
@@ -337,25 +681,31 @@ impl PluginScanner for WineVst3Scanner {
     }
 
     fn load(&self, info: &PluginInfo) -> Result<Self::Plugin> {
-        WineVst3Plugin::new(&self.host_exe, &info.path, self.wine_prefix.as_deref())
+        // Every plugin in a scan shares one Wine host, multiplexed by instance.
+        let server = self.shared_server()?;
+        WineVst3Plugin::on_server(server, &info.path)
     }
 }
 
 /// A Windows VST3 plugin loaded via Wine
 pub struct WineVst3Plugin {
-    /// Wine host process
-    _host_process: Child,
+    /// Shared Wine host this instance runs on; keeps the process alive for as
+    /// long as any handle to it exists (see [`WineHostServer`]).
+    _server: Arc<WineHostServer>,
     /// IPC client
     client: WineClient,
     /// Plugin info
     info: PluginInfo,
     /// Parameter IDs (indexed by parameter index)
     param_ids: Vec<u32>,
-    /// Shared memory path
-    shm_path: Option<String>,
-    /// Shared memory file descriptor (Linux side)
+    /// Owned descriptor backing the audio buffer (Linux side).
+    ///
+    /// Every audio path hands its sealed `memfd` to the host over `SCM_RIGHTS`
+    /// and closes this side's copy straight away, so this is `None` once audio
+    /// is initialized. No `/proc/self/fd/N` path to the buffer is given to the
+    /// host.
     #[cfg(target_os = "linux")]
-    shm_fd: Option<i32>,
+    shm_fd: Option<std::os::unix::io::OwnedFd>,
     /// Shared memory pointer
     shm_ptr: Option<*mut u8>,
     /// Shared memory size
@@ -365,45 +715,161 @@ pub struct WineVst3Plugin {
     block_size: usize,
     num_inputs: usize,
     num_outputs: usize,
+    /// Host capability flags from `GetInfo` (e.g. [`RACK_WINE_CAP_FD_PASSING`])
+    host_caps: u32,
+    /// Number of audio ring slots to request (0 selects the v1 flag layout).
+    ///
+    /// Gated off by default until the bundled Wine host speaks the
+    /// [`RACK_WINE_SHM_VERSION_RING`] layout; once it does, a non-zero depth
+    /// lets the client pipeline input blocks ahead of the host.
+    audio_ring_slots: u32,
+    /// SPSC audio ring over the shared mapping, when the ring layout is active.
+    #[cfg(target_os = "linux")]
+    ring: Option<ring::ShmRing>,
+    /// Use the shared-memory `host_ready`/`client_ready` flag handshake for
+    /// [`process`](PluginInstance::process) instead of a TCP `ProcessAudio`
+    /// round trip. Selected at [`initialize`](PluginInstance::initialize); the
+    /// TCP path stays as the fallback when this is `false`.
+    shm_handshake: bool,
     /// Initialized flag
     initialized: bool,
+    /// Last-known parameter values, keyed by parameter id, updated from
+    /// [`set_parameter`](PluginInstance::set_parameter) and
+    /// [`get_param_changes`](Self::get_param_changes). Replayed onto a respawned
+    /// host so a crash does not reset the plugin's automation state.
+    param_cache: HashMap<u32, f64>,
+    /// Reusable buffer of MIDI the plugin emitted during the last
+    /// [`process`](PluginInstance::process) block (arpeggiators, MIDI effects).
+    ///
+    /// Pre-sized to [`MIDI_OUT_CAPACITY`] and refilled in place each block so the
+    /// audio thread never allocates; a block that produces more events sets
+    /// [`midi_out_overflow`](Self::midi_out_overflow) rather than growing it.
+    midi_out: Vec<MidiEvent>,
+    /// Set when the last block produced more than [`MIDI_OUT_CAPACITY`] events,
+    /// so the surplus was dropped instead of growing [`midi_out`](Self::midi_out).
+    midi_out_overflow: bool,
+    /// Whether [`process`](PluginInstance::process) reads the shm MIDI-out
+    /// region after each block (see
+    /// [`set_midi_out_enabled`](Self::set_midi_out_enabled)).
+    ///
+    /// Reading costs nothing extra once the region exists, but it defaults to
+    /// off anyway: a caller that never reads [`output_midi`](Self::output_midi)
+    /// (most effects) has no reason to pay even the parsing cost, and only
+    /// instruments or MIDI effects that actually emit MIDI opt in.
+    midi_out_enabled: bool,
+    /// Optional callback invoked with the host's captured stderr when a crash is
+    /// detected (see [`health`](Self::health) and [`set_on_crash`](Self::set_on_crash)).
+    on_crash: Option<Arc<dyn Fn(&str) + Send + Sync>>,
 }
 
+/// Fixed capacity of the per-instance output MIDI buffer. A block that emits
+/// more events than this flags an overflow rather than reallocating on the
+/// audio thread.
+const MIDI_OUT_CAPACITY: usize = 256;
+
 // Safety: WineVst3Plugin is Send because:
 // - The Wine host runs in a separate process
 // - Communication is via TCP socket (Send)
 // - Shared memory is process-safe
 unsafe impl Send for WineVst3Plugin {}
 
-impl WineVst3Plugin {
-    /// Create a new Wine VST3 plugin instance
-    pub fn new(host_exe: &Path, plugin_path: &Path, wine_prefix: Option<&Path>) -> Result<Self> {
-        // Build wine command
-        let mut cmd = Command::new("wine");
-        cmd.arg(host_exe);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+/// Decode a raw MIDI status-plus-data slice back into a [`MidiEventKind`], the
+/// inverse of the status-nibble match in
+/// [`send_midi`](PluginInstance::send_midi). Returns `None` for an empty slice
+/// or an unrecognised status byte.
+fn decode_midi_kind(bytes: &[u8]) -> Option<crate::MidiEventKind> {
+    let status = *bytes.first()?;
+    let d1 = bytes.get(1).copied().unwrap_or(0);
+    let d2 = bytes.get(2).copied().unwrap_or(0);
+    let channel = status & 0x0F;
+    match status & 0xF0 {
+        0x80 => Some(crate::MidiEventKind::NoteOff { note: d1, velocity: d2, channel }),
+        0x90 => Some(crate::MidiEventKind::NoteOn { note: d1, velocity: d2, channel }),
+        0xA0 => Some(crate::MidiEventKind::PolyphonicAftertouch { note: d1, pressure: d2, channel }),
+        0xB0 => Some(crate::MidiEventKind::ControlChange { controller: d1, value: d2, channel }),
+        0xC0 => Some(crate::MidiEventKind::ProgramChange { program: d1, channel }),
+        0xD0 => Some(crate::MidiEventKind::ChannelAftertouch { pressure: d1, channel }),
+        0xE0 => Some(crate::MidiEventKind::PitchBend {
+            value: ((d1 as u16) & 0x7F) | (((d2 as u16) & 0x7F) << 7),
+            channel,
+        }),
+        0xF0 => Some(crate::MidiEventKind::SysEx { data: bytes.to_vec() }),
+        _ => None,
+    }
+}
 
-        if let Some(prefix) = wine_prefix {
-            cmd.env("WINEPREFIX", prefix);
-        }
+/// MTS "device ID" a Single Note Tuning Change targets; `0x7F` broadcasts to
+/// every receiver on the port.
+const MTS_DEVICE_ID_ALL: u8 = 0x7F;
+
+/// The `<n>` count field is a single 7-bit byte, so one Single Note Tuning
+/// Change message can describe at most this many key/pitch pairs.
+const MTS_MAX_CHANGES_PER_MESSAGE: usize = 127;
+
+/// Encode a real-time MTS Single Note Tuning Change as one or more SysEx byte
+/// slices, each `F0 7F <dev> 08 02 <program> <n> [kk xx yy zz]... F7`.
+///
+/// Each `(key, pitch)` retunes MIDI `key` to `pitch`, expressed in fractional
+/// semitones: `xx` is the integer semitone at or below the target and `yy zz`
+/// are a 14-bit fraction of that semitone, MSB first. Because the message is
+/// variable length it rides the SysEx path the same way a raw dump does. The
+/// `<n>` count is a 7-bit field, so `changes` longer than
+/// [`MTS_MAX_CHANGES_PER_MESSAGE`] is split across multiple messages rather
+/// than wrapping the count.
+fn encode_mts_single_note_tuning(program: u8, changes: &[(u8, f64)]) -> Vec<Vec<u8>> {
+    changes
+        .chunks(MTS_MAX_CHANGES_PER_MESSAGE)
+        .map(|chunk| {
+            let mut out = Vec::with_capacity(8 + chunk.len() * 4);
+            out.push(0xF0);
+            out.push(0x7F);
+            out.push(MTS_DEVICE_ID_ALL);
+            out.push(0x08);
+            out.push(0x02);
+            out.push(program & 0x7F);
+            out.push(chunk.len() as u8);
+            for &(key, pitch) in chunk {
+                out.push(key & 0x7F);
+                let (xx, yy, zz) = encode_mts_pitch(pitch);
+                out.push(xx);
+                out.push(yy);
+                out.push(zz);
+            }
+            out.push(0xF7);
+            out
+        })
+        .collect()
+}
 
-        // Spawn the host
-        let host_process = cmd.spawn()
-            .map_err(|e| Error::Other(format!("Failed to spawn Wine host: {}", e)))?;
+/// Serialize a fractional MIDI pitch as the MTS three-byte `xx yy zz` form: the
+/// integer semitone nearest below the target followed by a 14-bit fraction of
+/// that semitone. A target at or above 127 maps to the reserved `127 7F 7F`
+/// "no change" triple.
+fn encode_mts_pitch(pitch: f64) -> (u8, u8, u8) {
+    let pitch = pitch.clamp(0.0, 127.0);
+    if pitch >= 127.0 {
+        return (127, 0x7F, 0x7F);
+    }
+    let semitone = pitch.floor();
+    let units = ((pitch - semitone) * 16384.0).round() as i64;
+    let units = units.clamp(0, 16383) as u16;
+    (semitone as u8 & 0x7F, ((units >> 7) & 0x7F) as u8, (units & 0x7F) as u8)
+}
 
-        // Wait for host to start
-        std::thread::sleep(Duration::from_millis(2000));
+impl WineVst3Plugin {
+    /// Create a new Wine VST3 plugin instance on its own dedicated host.
+    ///
+    /// Spawns a single-instance [`WineHostServer`] and loads the plugin onto it.
+    /// To share one host across many plugins, load through [`WineVst3Scanner`]
+    /// instead, which hands out instances on a common server.
+    pub fn new(host_exe: &Path, plugin_path: &Path, wine_prefix: Option<&Path>) -> Result<Self> {
+        let server = WineHostServer::spawn(host_exe, wine_prefix)?;
+        Self::on_server(server, plugin_path)
+    }
 
-        // Connect to host
-        let mut client = None;
-        for port in RACK_WINE_PORT_BASE..=RACK_WINE_PORT_MAX {
-            if let Ok(c) = WineClient::connect(port) {
-                client = Some(c);
-                break;
-            }
-        }
-        let mut client = client.ok_or_else(|| Error::Other("Failed to connect to Wine host".to_string()))?;
+    /// Load a plugin as a new instance on an existing shared host.
+    fn on_server(server: Arc<WineHostServer>, plugin_path: &Path) -> Result<Self> {
+        let mut client = server.new_client();
 
         // Ping to verify connection
         client.ping()?;
@@ -421,14 +887,12 @@ impl WineVst3Plugin {
         // Get plugin info
         let host_info = client.get_info()?;
 
-        // Get parameter IDs
-        let param_count = host_info.num_params;
-        let mut param_ids = Vec::with_capacity(param_count as usize);
-        for i in 0..param_count {
-            if let Ok(info) = client.get_param_info(i) {
-                param_ids.push(info.id);
-            }
-        }
+        // Get parameter IDs (pipelined: all requests are sent before collecting)
+        let param_ids: Vec<u32> = client
+            .get_all_param_info(host_info.num_params)
+            .into_iter()
+            .map(|info| info.id)
+            .collect();
 
         let info = PluginInfo {
             name: host_info.name,
@@ -440,11 +904,10 @@ impl WineVst3Plugin {
         };
 
         Ok(Self {
-            _host_process: host_process,
+            _server: server,
             client,
             info,
             param_ids,
-            shm_path: None,
             #[cfg(target_os = "linux")]
             shm_fd: None,
             shm_ptr: None,
@@ -453,10 +916,189 @@ impl WineVst3Plugin {
             block_size: 0,
             num_inputs: host_info.num_audio_inputs as usize,
             num_outputs: host_info.num_audio_outputs as usize,
+            host_caps: host_info.flags,
+            audio_ring_slots: 0,
+            #[cfg(target_os = "linux")]
+            ring: None,
+            shm_handshake: false,
             initialized: false,
+            param_cache: HashMap::new(),
+            midi_out: Vec::with_capacity(MIDI_OUT_CAPACITY),
+            midi_out_overflow: false,
+            midi_out_enabled: false,
+            on_crash: None,
         })
     }
 
+    /// Register a callback invoked with the host's captured stderr whenever a
+    /// crash is detected (see [`health`](Self::health)), so a host application
+    /// can surface the failure to the user.
+    pub fn set_on_crash(&mut self, callback: impl Fn(&str) + Send + Sync + 'static) {
+        self.on_crash = Some(Arc::new(callback));
+    }
+
+    /// Opt this instance into reading the shm MIDI-out region after every
+    /// block (see [`output_midi`](Self::output_midi)).
+    ///
+    /// Off by default since most effects never produce MIDI and have no
+    /// reason to pay even the parsing cost; enable it only for instruments or
+    /// MIDI effects that actually emit MIDI. Also requires the host to
+    /// advertise [`RACK_WINE_CAP_MIDI_OUT`].
+    pub fn set_midi_out_enabled(&mut self, enabled: bool) {
+        self.midi_out_enabled = enabled;
+    }
+
+    /// Check that the Wine host is still alive, recovering it if not.
+    ///
+    /// Returns `Ok(())` while the host is running. On an unexpected exit the
+    /// captured stderr is reported through the [`on_crash`](Self::set_on_crash)
+    /// callback; if this instance had been initialized the host is respawned,
+    /// the plugin reloaded and reinitialized with the same audio configuration,
+    /// and the cached parameter values replayed, so a crashing plugin does not
+    /// take down the audio graph. If recovery is not possible the crash is
+    /// returned as an [`Error`] carrying the stderr tail.
+    pub fn health(&mut self) -> Result<()> {
+        if self._server.is_alive() {
+            return Ok(());
+        }
+        self.recover_from_crash()
+    }
+
+    /// MIDI the plugin emitted during the last processed block.
+    ///
+    /// Valid until the next [`process`](PluginInstance::process) call, which
+    /// refills the buffer in place. Empty when the plugin produced no output.
+    pub fn output_midi(&self) -> &[MidiEvent] {
+        &self.midi_out
+    }
+
+    /// Whether the last block produced more than [`MIDI_OUT_CAPACITY`] events,
+    /// in which case [`output_midi`](Self::output_midi) holds only the first
+    /// [`MIDI_OUT_CAPACITY`] of them.
+    pub fn output_midi_overflowed(&self) -> bool {
+        self.midi_out_overflow
+    }
+
+    /// Drain the plugin's output MIDI for the last block into the reusable
+    /// [`midi_out`](Self::midi_out) buffer.
+    ///
+    /// No-op unless [`set_midi_out_enabled`](Self::set_midi_out_enabled) was
+    /// called and the host advertises [`RACK_WINE_CAP_MIDI_OUT`]. The host
+    /// writes events directly into the [`ShmMidiOutHeader`] region trailing
+    /// the audio buffers (see [`ShmHeader::midi_out_offset`]), so this only
+    /// parses already-resident shared memory — no RPC, no allocation, safe to
+    /// call every block on the audio thread. The buffer is refilled in place
+    /// and never grown: events past [`MIDI_OUT_CAPACITY`] are dropped and the
+    /// overflow flag is raised, same as a region the host itself overflowed.
+    /// A mapping with no MIDI-out region (`midi_out_offset == 0`, e.g. the
+    /// ring layout) or a malformed one leaves the buffer empty rather than
+    /// erroring the audio block.
+    fn collect_output_midi(&mut self) {
+        self.midi_out.clear();
+        self.midi_out_overflow = false;
+
+        if !self.midi_out_enabled || self.host_caps & RACK_WINE_CAP_MIDI_OUT == 0 {
+            return;
+        }
+        let Some(shm_ptr) = self.shm_ptr else { return };
+
+        let midi_out_offset = unsafe { (*(shm_ptr as *const ShmHeader)).midi_out_offset.get() } as usize;
+        if midi_out_offset == 0 || midi_out_offset + ShmMidiOutHeader::SIZE > self.shm_size {
+            return;
+        }
+
+        let region = unsafe { shm_ptr.add(midi_out_offset) };
+        let midi_header = region as *const ShmMidiOutHeader;
+        let num_events = unsafe { (*midi_header).num_events.get() } as usize;
+        if unsafe { (*midi_header).overflow.get() } != 0 {
+            self.midi_out_overflow = true;
+        }
+
+        let payload_len = self.shm_size - midi_out_offset - ShmMidiOutHeader::SIZE;
+        let payload = unsafe {
+            std::slice::from_raw_parts(region.add(ShmMidiOutHeader::SIZE), payload_len)
+        };
+
+        let mut off = 0;
+        for _ in 0..num_events {
+            if off + MidiEventHeader::SIZE > payload.len() {
+                break;
+            }
+            let sample_offset = u32::from_le_bytes([
+                payload[off], payload[off + 1], payload[off + 2], payload[off + 3],
+            ]);
+            let length = u32::from_le_bytes([
+                payload[off + 4], payload[off + 5], payload[off + 6], payload[off + 7],
+            ]) as usize;
+            off += MidiEventHeader::SIZE;
+            if off + length > payload.len() {
+                break;
+            }
+            let bytes = &payload[off..off + length];
+            off += length;
+
+            if let Some(kind) = decode_midi_kind(bytes) {
+                if self.midi_out.len() == MIDI_OUT_CAPACITY {
+                    self.midi_out_overflow = true;
+                    break;
+                }
+                self.midi_out.push(MidiEvent { sample_offset, kind });
+            }
+        }
+    }
+
+    /// Respawn the host and restore this instance after an observed crash.
+    fn recover_from_crash(&mut self) -> Result<()> {
+        let stderr = self._server.stderr_tail();
+        if let Some(callback) = self.on_crash.clone() {
+            callback(&stderr);
+        }
+        if !self.initialized {
+            return Err(Error::Other(format!("Wine host crashed: {}", stderr)));
+        }
+
+        // Spawn a replacement host with the same command line and reload.
+        let server = WineHostServer::spawn(
+            &self._server.host_exe,
+            self._server.wine_prefix.as_deref(),
+        )?;
+        let mut client = server.new_client();
+        client.ping()?;
+        let wine_path = if self.info.path.starts_with("/") {
+            format!("Z:{}", self.info.path.display())
+        } else {
+            self.info.path.display().to_string()
+        };
+        client.load_plugin(&wine_path, 0)?;
+
+        // Drop the stale mapping before swapping in the fresh host; the new
+        // initialize below allocates its own buffer.
+        #[cfg(target_os = "linux")]
+        if let Some(ptr) = self.shm_ptr.take() {
+            unsafe { libc::munmap(ptr as *mut libc::c_void, self.shm_size) };
+        }
+        #[cfg(target_os = "linux")]
+        {
+            self.ring = None;
+            self.shm_fd = None;
+        }
+        self.shm_size = 0;
+
+        self._server = server;
+        self.client = client;
+
+        // Reinitialize audio with the same configuration, then replay the
+        // last-known parameter values so automation state survives the crash.
+        let (sample_rate, block_size) = (self.sample_rate, self.block_size);
+        self.initialized = false;
+        <Self as PluginInstance>::initialize(self, sample_rate, block_size)?;
+        let cached: Vec<(u32, f64)> = self.param_cache.iter().map(|(&id, &v)| (id, v)).collect();
+        for (id, value) in cached {
+            let _ = self.client.set_param(id, value);
+        }
+        Ok(())
+    }
+
     /// Open the plugin editor
     pub fn open_editor(&mut self) -> Result<(u32, u32, u32)> {
         let info = self.client.open_editor()?;
@@ -468,6 +1110,87 @@ impl WineVst3Plugin {
         self.client.close_editor()
     }
 
+    /// Open the plugin editor in offscreen mode, returning a framebuffer surface
+    /// the caller composites itself.
+    ///
+    /// Unlike [`open_editor`](Self::open_editor), which returns an X11 window id
+    /// to embed, this allocates a sealed RGBA framebuffer, hands it to the host
+    /// over the fd-passing control socket, and lets the host render each dirtied
+    /// frame into it. A Wayland or headless caller blits from the returned
+    /// [`EditorSurface`] and feeds input back with
+    /// [`EditorSurface::push_input_event`]. Requires a host advertising
+    /// [`RACK_WINE_CAP_OFFSCREEN_EDITOR`] and a live control socket.
+    #[cfg(target_os = "linux")]
+    pub fn open_editor_offscreen(&mut self) -> Result<EditorSurface> {
+        use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+        if self.host_caps & RACK_WINE_CAP_OFFSCREEN_EDITOR == 0 {
+            return Err(Error::Other("Host does not support offscreen editors".to_string()));
+        }
+
+        // Size the framebuffer to the editor's preferred geometry, packed RGBA.
+        let size = self.client.get_editor_size()?;
+        let width = size.width.max(1);
+        let height = size.height.max(1);
+        let stride = width * 4;
+        let total_size = FbHeader::SIZE + stride as usize * height as usize;
+
+        let fd = fd_passing::create_sealed_memfd("rack-wine-editor-fb", total_size)?;
+        // Safety: create_sealed_memfd hands back a fresh owned descriptor.
+        let owned = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                total_size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                owned.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::Other("Failed to mmap editor framebuffer".to_string()));
+        }
+
+        // Lay out the framebuffer header before the host starts painting.
+        let header = ptr as *mut FbHeader;
+        unsafe {
+            (*header).magic = U32::new(RACK_WINE_FB_MAGIC);
+            (*header).width = U32::new(width);
+            (*header).height = U32::new(height);
+            (*header).stride = U32::new(stride);
+            (*header).sequence = U32::new(0);
+            (*header).damage_x = U32::new(0);
+            (*header).damage_y = U32::new(0);
+            (*header).damage_w = U32::new(width);
+            (*header).damage_h = U32::new(height);
+            (*header).reserved = U32::new(0);
+        }
+
+        if let Err(e) = self
+            .client
+            .open_editor_offscreen_fd(width, height, stride, owned.as_raw_fd())
+        {
+            unsafe { libc::munmap(ptr, total_size) };
+            return Err(e);
+        }
+
+        // Safety: the mapping is `total_size` bytes laid out with the geometry above.
+        let fb = unsafe { framebuffer::ShmFramebuffer::new(ptr as *mut u8, stride, height) };
+        Ok(EditorSurface {
+            ptr: ptr as *mut u8,
+            size: total_size,
+            width,
+            height,
+            stride,
+            fb,
+            _fd: owned,
+            rpc: Arc::clone(&self.client.rpc),
+            instance_id: self.client.instance_id,
+        })
+    }
+
     /// Get parameter changes from GUI since last poll
     ///
     /// Returns a list of (param_id, value) tuples for parameters that were
@@ -475,134 +1198,129 @@ impl WineVst3Plugin {
     /// audio buffer or on a timer) to stay in sync with GUI changes.
     pub fn get_param_changes(&mut self) -> Result<Vec<(u32, f64)>> {
         let changes = self.client.get_param_changes()?;
+        // Fold GUI-driven changes into the replay cache as well.
+        for change in &changes {
+            self.param_cache.insert(change.param_id, change.value);
+        }
         Ok(changes.into_iter().map(|c| (c.param_id, c.value)).collect())
     }
 
+    /// Create the audio buffer as a sealed `memfd`, map it, and lay out the header.
+    ///
+    /// There is no on-disk artifact to name or clean up: the returned
+    /// descriptor is handed to the host over the control socket as
+    /// `SCM_RIGHTS` ancillary data, and the kernel frees the buffer once both
+    /// sides close it. The caller owns the fd until it has been sent. This is
+    /// the only way audio shared memory is wired up; a host that doesn't
+    /// advertise [`RACK_WINE_CAP_FD_PASSING`] cannot be used for audio, since a
+    /// `memfd`'s `/proc/self/fd/N` entry is only resolvable by the owning
+    /// process.
     #[cfg(target_os = "linux")]
-    fn setup_shared_memory(&mut self, block_size: usize, num_inputs: usize, num_outputs: usize) -> Result<String> {
-        use std::os::unix::io::AsRawFd;
-
-        // Generate unique shared memory name
-        let counter = SHM_COUNTER.fetch_add(1, Ordering::SeqCst);
-        let pid = std::process::id();
-        let shm_name = format!("/tmp/rack-wine-audio-{}-{}", pid, counter);
-
-        // Calculate size
+    fn setup_shared_memory_memfd(&mut self, block_size: usize, num_inputs: usize, num_outputs: usize) -> Result<std::os::unix::io::RawFd> {
         let header_size = ShmHeader::SIZE;
         let buffer_size = (num_inputs + num_outputs) * block_size * std::mem::size_of::<f32>();
-        let total_size = header_size + buffer_size;
+        let midi_out_offset = header_size + buffer_size;
+        let total_size = midi_out_offset + SHM_MIDI_OUT_REGION_BYTES;
 
-        // Create and map shared memory using a regular file
-        let file = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&shm_name)
-            .map_err(|e| Error::Other(format!("Failed to create shared memory file: {}", e)))?;
+        let fd = fd_passing::create_sealed_memfd("rack-wine-audio", total_size)?;
 
-        // Set size
-        file.set_len(total_size as u64)
-            .map_err(|e| Error::Other(format!("Failed to set shared memory size: {}", e)))?;
-
-        // Memory map
         let ptr = unsafe {
             libc::mmap(
                 std::ptr::null_mut(),
                 total_size,
                 libc::PROT_READ | libc::PROT_WRITE,
                 libc::MAP_SHARED,
-                file.as_raw_fd(),
+                fd,
                 0,
             )
         };
 
         if ptr == libc::MAP_FAILED {
-            return Err(Error::Other("Failed to mmap shared memory".to_string()));
+            unsafe { libc::close(fd) };
+            return Err(Error::Other("Failed to mmap audio memfd".to_string()));
         }
 
-        // Initialize header
-        let header = ptr as *mut ShmHeader;
-        unsafe {
-            (*header).magic = RACK_WINE_SHM_MAGIC;
-            (*header).version = RACK_WINE_PROTOCOL_VERSION;
-            (*header).num_inputs = num_inputs as u32;
-            (*header).num_outputs = num_outputs as u32;
-            (*header).block_size = block_size as u32;
-            (*header).sample_rate = self.sample_rate as u32;
-            (*header).host_ready = 0;
-            (*header).client_ready = 0;
-            (*header).input_offset = header_size as u32;
-            (*header).output_offset = (header_size + num_inputs * block_size * std::mem::size_of::<f32>()) as u32;
-        }
-
-        self.shm_fd = Some(file.as_raw_fd());
+        self.init_shm_header(ptr as *mut u8, header_size, block_size, num_inputs, num_outputs, midi_out_offset);
+
+        // The descriptor is handed to the host over SCM_RIGHTS and closed by the
+        // caller, so it is not retained in `shm_fd`.
         self.shm_ptr = Some(ptr as *mut u8);
         self.shm_size = total_size;
-        self.shm_path = Some(shm_name.clone());
 
-        // Don't close the file - keep it open for the fd
-        std::mem::forget(file);
-
-        Ok(shm_name)
+        Ok(fd)
     }
 
-    #[cfg(not(target_os = "linux"))]
-    fn setup_shared_memory(&mut self, _block_size: usize, _num_inputs: usize, _num_outputs: usize) -> Result<String> {
-        Err(Error::Other("Wine VST3 host only supported on Linux".to_string()))
-    }
-}
-
-impl Drop for WineVst3Plugin {
-    fn drop(&mut self) {
-        // Try to shutdown gracefully
-        let _ = self.client.shutdown();
+    /// Allocate a ring-layout buffer and publish it to the host over the control
+    /// socket, leaving `self.ring` ready for [`process`](PluginInstance::process).
+    ///
+    /// The mapping is `ShmHeader` followed by `num_slots` slots, each a
+    /// [`ShmSlotHeader`] plus the per-block input and output sample regions.
+    #[cfg(target_os = "linux")]
+    fn setup_audio_ring(&mut self, block_size: usize, num_slots: u32) -> Result<()> {
+        let input_bytes = self.num_inputs * block_size * std::mem::size_of::<f32>();
+        let output_bytes = self.num_outputs * block_size * std::mem::size_of::<f32>();
+        let slot_stride = ShmSlotHeader::SIZE + input_bytes + output_bytes;
+        let total_size = ShmHeader::SIZE + num_slots as usize * slot_stride;
 
-        // Cleanup shared memory
-        #[cfg(target_os = "linux")]
-        if let Some(ptr) = self.shm_ptr {
-            unsafe {
-                libc::munmap(ptr as *mut libc::c_void, self.shm_size);
-            }
+        let fd = fd_passing::create_sealed_memfd("rack-wine-audio-ring", total_size)?;
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                total_size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            unsafe { libc::close(fd) };
+            return Err(Error::Other("Failed to mmap audio ring".to_string()));
         }
 
-        #[cfg(target_os = "linux")]
-        if let Some(path) = &self.shm_path {
-            let _ = std::fs::remove_file(path);
+        // Lay out the header with the ring version and slot geometry. The ring
+        // layout has no MIDI-out region yet (`midi_out_offset` stays 0), so
+        // `collect_output_midi` no-ops for ring-backed instances.
+        self.init_shm_header(ptr as *mut u8, ShmHeader::SIZE, block_size, self.num_inputs, self.num_outputs, 0);
+        let header = ptr as *mut ShmHeader;
+        unsafe {
+            (*header).version = U32::new(RACK_WINE_SHM_VERSION_RING);
+            (*header).num_slots = U32::new(num_slots);
+            (*header).slot_stride = U32::new(slot_stride as u32);
+            (*header).write_index = zerocopy::byteorder::U64::new(0);
+            (*header).read_index = zerocopy::byteorder::U64::new(0);
         }
-    }
-}
-
-impl PluginInstance for WineVst3Plugin {
-    fn initialize(&mut self, sample_rate: f64, max_block_size: usize) -> Result<()> {
-        self.sample_rate = sample_rate;
-        self.block_size = max_block_size;
 
-        // Setup shared memory
-        let shm_name = self.setup_shared_memory(max_block_size, self.num_inputs, self.num_outputs)?;
-
-        // Convert path for Wine (Z: drive prefix)
-        let wine_shm_name = format!("Z:{}", shm_name);
+        self.shm_ptr = Some(ptr as *mut u8);
+        self.shm_size = total_size;
+        self.ring = Some(unsafe { ring::ShmRing::new(ptr as *mut u8, num_slots, input_bytes, output_bytes) });
 
-        // Initialize audio on host
-        self.client.init_audio(
-            sample_rate as u32,
-            max_block_size as u32,
+        let result = self.client.init_audio_fd(
+            self.sample_rate as u32,
+            block_size as u32,
             self.num_inputs as u32,
             self.num_outputs as u32,
-            &wine_shm_name,
-        )?;
-
-        self.initialized = true;
-        Ok(())
-    }
-
-    fn reset(&mut self) -> Result<()> {
-        // No explicit reset command in the protocol
-        // Could reinitialize if needed
-        Ok(())
+            fd,
+        );
+        unsafe { libc::close(fd) };
+        self.shm_fd = None;
+
+        if result.is_err() {
+            // The host never took the buffer; undo the mapping so a later
+            // process() doesn't publish into a ring no one is draining.
+            unsafe { libc::munmap(ptr, total_size) };
+            self.ring = None;
+            self.shm_ptr = None;
+            self.shm_size = 0;
+        }
+        result
     }
 
-    fn process(
+    /// Route one audio block to whichever transport `initialize` selected.
+    ///
+    /// Wrapped by [`process`](PluginInstance::process), which watches the result
+    /// for a crashed host and recovers transparently.
+    fn process_dispatch(
         &mut self,
         inputs: &[&[f32]],
         outputs: &mut [&mut [f32]],
@@ -612,13 +1330,28 @@ impl PluginInstance for WineVst3Plugin {
             return Err(Error::NotInitialized);
         }
 
+        // Ring path: publish the input block and park until the host hands the
+        // processed slot back, with no TCP round trip on the audio thread.
+        #[cfg(target_os = "linux")]
+        if self.ring.is_some() {
+            return self.process_ring(inputs, outputs, num_frames);
+        }
+
         let shm_ptr = self.shm_ptr.ok_or(Error::NotInitialized)?;
 
+        // Flag-handshake path: signal the host through the shared flags and wait
+        // for it in shared memory, keeping the kernel networking stack off the
+        // hot path. The TCP `ProcessAudio` round trip below is the fallback.
+        #[cfg(target_os = "linux")]
+        if self.shm_handshake {
+            return self.process_flags(shm_ptr, inputs, outputs, num_frames);
+        }
+
         // Read header to get offsets
         let header = unsafe { &*(shm_ptr as *const ShmHeader) };
-        let input_offset = header.input_offset as usize;
-        let output_offset = header.output_offset as usize;
-        let block_size = header.block_size as usize;
+        let input_offset = header.input_offset.get() as usize;
+        let output_offset = header.output_offset.get() as usize;
+        let block_size = header.block_size.get() as usize;
 
         // Copy input data to shared memory
         for (ch, input) in inputs.iter().enumerate() {
@@ -656,6 +1389,362 @@ impl PluginInstance for WineVst3Plugin {
         Ok(())
     }
 
+    /// Drive one audio block through the SPSC ring.
+    #[cfg(target_os = "linux")]
+    fn process_ring(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], num_frames: usize) -> Result<()> {
+        let block_size = self.block_size;
+        let num_inputs = self.num_inputs;
+        let num_outputs = self.num_outputs;
+        let ring = self.ring.as_ref().ok_or(Error::NotInitialized)?;
+
+        // Copy inputs into the producer slot (planar, one block per channel).
+        {
+            let slot = ring.input_slot();
+            let samples = unsafe {
+                std::slice::from_raw_parts_mut(slot.as_mut_ptr() as *mut f32, slot.len() / std::mem::size_of::<f32>())
+            };
+            // Slots are reused, so clear stale audio from uncovered channels and
+            // the tail beyond num_frames before filling in this block.
+            samples.fill(0.0);
+            for (ch, input) in inputs.iter().enumerate().take(num_inputs) {
+                let base = ch * block_size;
+                let copy_len = num_frames.min(input.len());
+                samples[base..base + copy_len].copy_from_slice(&input[..copy_len]);
+            }
+        }
+
+        let generation = ring.publish(num_frames as u32);
+        ring.wait_consumed(generation, Duration::from_millis(200))?;
+
+        // Copy processed outputs back out of the same slot.
+        let slot = ring.output_slot(generation);
+        let samples = unsafe {
+            std::slice::from_raw_parts(slot.as_ptr() as *const f32, slot.len() / std::mem::size_of::<f32>())
+        };
+        for (ch, output) in outputs.iter_mut().enumerate().take(num_outputs) {
+            let base = ch * block_size;
+            let copy_len = num_frames.min(output.len());
+            output[..copy_len].copy_from_slice(&samples[base..base + copy_len]);
+        }
+
+        Ok(())
+    }
+
+    /// Drive one audio block through the `host_ready`/`client_ready` flag
+    /// handshake in shared memory, with no TCP round trip.
+    #[cfg(target_os = "linux")]
+    fn process_flags(&mut self, shm_ptr: *mut u8, inputs: &[&[f32]], outputs: &mut [&mut [f32]], num_frames: usize) -> Result<()> {
+        let header = shm_ptr as *mut ShmHeader;
+        let input_offset = unsafe { (*header).input_offset.get() } as usize;
+        let output_offset = unsafe { (*header).output_offset.get() } as usize;
+        let block_size = unsafe { (*header).block_size.get() } as usize;
+
+        // Copy inputs into shared memory (planar, one block per channel).
+        for (ch, input) in inputs.iter().enumerate() {
+            if ch < self.num_inputs {
+                let dest_offset = input_offset + ch * block_size * std::mem::size_of::<f32>();
+                let dest = unsafe {
+                    std::slice::from_raw_parts_mut(shm_ptr.add(dest_offset) as *mut f32, num_frames)
+                };
+                let copy_len = num_frames.min(input.len());
+                dest[..copy_len].copy_from_slice(&input[..copy_len]);
+            }
+        }
+
+        // Safety: these fields are 32-bit and, on the little-endian x86_64
+        // targets the Wine host runs on, alias an AtomicU32.
+        let client_ready = unsafe { &*(std::ptr::addr_of!((*header).client_ready) as *const AtomicU32) };
+        let host_ready = unsafe { &*(std::ptr::addr_of!((*header).host_ready) as *const AtomicU32) };
+        let frames = unsafe { &*(std::ptr::addr_of!((*header).process_frames) as *const AtomicU32) };
+
+        // Publish the block: frame count first, then release the generation so
+        // the host observes the samples once it sees the new client_ready.
+        frames.store(num_frames as u32, Ordering::Relaxed);
+        let generation = client_ready.load(Ordering::Relaxed).wrapping_add(1);
+        client_ready.store(generation, Ordering::Release);
+        ring::futex_wake(client_ready as *const AtomicU32 as *const u32);
+
+        self.wait_host(host_ready, generation)?;
+
+        // Copy processed outputs back out of shared memory.
+        for (ch, output) in outputs.iter_mut().enumerate() {
+            if ch < self.num_outputs {
+                let src_offset = output_offset + ch * block_size * std::mem::size_of::<f32>();
+                let src = unsafe {
+                    std::slice::from_raw_parts(shm_ptr.add(src_offset) as *const f32, num_frames)
+                };
+                let copy_len = num_frames.min(output.len());
+                output[..copy_len].copy_from_slice(&src[..copy_len]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spin briefly, then park on `host_ready` until it reaches `generation` or
+    /// the bounded timeout elapses (so a crashed host surfaces as an error).
+    #[cfg(target_os = "linux")]
+    fn wait_host(&self, host_ready: &std::sync::atomic::AtomicU32, generation: u32) -> Result<()> {
+        use std::time::Instant;
+        const SPIN_LIMIT: u32 = 400;
+
+        for _ in 0..SPIN_LIMIT {
+            if host_ready.load(Ordering::Acquire) == generation {
+                return Ok(());
+            }
+            std::hint::spin_loop();
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(200);
+        loop {
+            let observed = host_ready.load(Ordering::Acquire);
+            if observed == generation {
+                return Ok(());
+            }
+            let remaining = match deadline.checked_duration_since(Instant::now()) {
+                Some(d) if !d.is_zero() => d,
+                _ => return Err(Error::Other("Wine host audio handshake timed out".to_string())),
+            };
+            ring::futex_wait(
+                host_ready as *const std::sync::atomic::AtomicU32 as *const u32,
+                observed,
+                remaining,
+            );
+        }
+    }
+
+    /// Populate the [`ShmHeader`] at the start of a freshly mapped buffer.
+    ///
+    /// `midi_out_offset` is the byte offset of the trailing
+    /// [`ShmMidiOutHeader`] region, or 0 for a mapping that doesn't carry one.
+    #[cfg(target_os = "linux")]
+    fn init_shm_header(&self, ptr: *mut u8, header_size: usize, block_size: usize, num_inputs: usize, num_outputs: usize, midi_out_offset: usize) {
+        let header = ptr as *mut ShmHeader;
+        unsafe {
+            (*header).magic = U32::new(RACK_WINE_SHM_MAGIC);
+            (*header).version = U32::new(RACK_WINE_SHM_VERSION_FLAGS);
+            (*header).num_inputs = U32::new(num_inputs as u32);
+            (*header).num_outputs = U32::new(num_outputs as u32);
+            (*header).block_size = U32::new(block_size as u32);
+            (*header).sample_rate = U32::new(self.sample_rate as u32);
+            (*header).host_ready = U32::new(0);
+            (*header).client_ready = U32::new(0);
+            (*header).input_offset = U32::new(header_size as u32);
+            (*header).output_offset = U32::new((header_size + num_inputs * block_size * std::mem::size_of::<f32>()) as u32);
+            (*header).midi_out_offset = U32::new(midi_out_offset as u32);
+        }
+    }
+}
+
+/// A mouse or keyboard event relayed into an offscreen editor.
+///
+/// Coordinates are in framebuffer pixels; `modifiers` is a host-defined bitmask
+/// of held modifier keys. Serialized to a [`CmdInputEvent`] on the wire.
+#[derive(Debug, Clone, Copy)]
+pub enum EditorInputEvent {
+    MouseMove { x: u32, y: u32, modifiers: u32 },
+    MouseDown { x: u32, y: u32, button: u32, modifiers: u32 },
+    MouseUp { x: u32, y: u32, button: u32, modifiers: u32 },
+    MouseWheel { x: u32, y: u32, delta: f64, modifiers: u32 },
+    KeyDown { key: u32, modifiers: u32 },
+    KeyUp { key: u32, modifiers: u32 },
+}
+
+impl EditorInputEvent {
+    fn to_wire(self) -> CmdInputEvent {
+        match self {
+            EditorInputEvent::MouseMove { x, y, modifiers } => {
+                CmdInputEvent::new(RACK_WINE_INPUT_MOUSE_MOVE, 0, x, y, modifiers, 0.0)
+            }
+            EditorInputEvent::MouseDown { x, y, button, modifiers } => {
+                CmdInputEvent::new(RACK_WINE_INPUT_MOUSE_DOWN, button, x, y, modifiers, 0.0)
+            }
+            EditorInputEvent::MouseUp { x, y, button, modifiers } => {
+                CmdInputEvent::new(RACK_WINE_INPUT_MOUSE_UP, button, x, y, modifiers, 0.0)
+            }
+            EditorInputEvent::MouseWheel { x, y, delta, modifiers } => {
+                CmdInputEvent::new(RACK_WINE_INPUT_MOUSE_WHEEL, 0, x, y, modifiers, delta)
+            }
+            EditorInputEvent::KeyDown { key, modifiers } => {
+                CmdInputEvent::new(RACK_WINE_INPUT_KEY_DOWN, key, 0, 0, modifiers, 0.0)
+            }
+            EditorInputEvent::KeyUp { key, modifiers } => {
+                CmdInputEvent::new(RACK_WINE_INPUT_KEY_UP, key, 0, 0, modifiers, 0.0)
+            }
+        }
+    }
+}
+
+/// A client-owned RGBA framebuffer into which the host renders an offscreen
+/// editor, returned by [`WineVst3Plugin::open_editor_offscreen`].
+///
+/// The caller blits from the mapping itself — either directly via
+/// [`as_pixels_ptr`](Self::as_pixels_ptr) or tear-free via
+/// [`read_frame`](Self::read_frame) — and relays input back with
+/// [`push_input_event`](Self::push_input_event). Dropping the surface closes the
+/// editor on the host and unmaps the buffer; the backing memfd is reclaimed once
+/// both sides release it.
+#[cfg(target_os = "linux")]
+pub struct EditorSurface {
+    ptr: *mut u8,
+    size: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+    fb: framebuffer::ShmFramebuffer,
+    /// Owned framebuffer descriptor; kept alive for the surface's lifetime.
+    _fd: std::os::unix::io::OwnedFd,
+    /// Shared command transport, used to relay input and close the editor.
+    rpc: Arc<RpcClient>,
+    instance_id: u32,
+}
+
+#[cfg(target_os = "linux")]
+pub use framebuffer::DamageRect;
+
+#[cfg(target_os = "linux")]
+impl EditorSurface {
+    /// Framebuffer geometry as `(width, height, stride_bytes)`.
+    pub fn size(&self) -> (u32, u32, u32) {
+        (self.width, self.height, self.stride)
+    }
+
+    /// Raw pointer to the first pixel, for callers that blit in place from the
+    /// mapping and tolerate the small tearing risk of not snapshotting.
+    pub fn as_pixels_ptr(&self) -> *const u8 {
+        self.fb.pixels_ptr()
+    }
+
+    /// Length in bytes of the pixel region (`stride * height`).
+    pub fn pixels_len(&self) -> usize {
+        self.fb.pixels_len()
+    }
+
+    /// Copy the latest published frame into `dst` without tearing, returning its
+    /// sequence number and damage rectangle, or `None` if `dst` is too small.
+    pub fn read_frame(&self, dst: &mut [u8]) -> Option<(u32, DamageRect)> {
+        self.fb.read_frame(dst)
+    }
+
+    /// Relay a mouse or keyboard event into the offscreen editor.
+    pub fn push_input_event(&self, event: EditorInputEvent) -> Result<()> {
+        self.rpc
+            .call(HostCommand::SendInputEvent, event.to_wire().as_bytes(), self.instance_id)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for EditorSurface {
+    fn drop(&mut self) {
+        // Ask the host to tear down the offscreen editor, then unmap; the memfd
+        // is freed once the host drops its dup and `_fd` drops with `self`.
+        let _ = self.rpc.call(HostCommand::CloseEditor, &[], self.instance_id);
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.size);
+        }
+    }
+}
+
+impl Drop for WineVst3Plugin {
+    fn drop(&mut self) {
+        // Unload just this instance; the shared host stays up for the others
+        // and is shut down only when the last [`WineHostServer`] handle drops.
+        let _ = self.client.unload_plugin();
+
+        // Unmap the buffer. Our copy of the backing memfd was closed once it was
+        // handed to the host, so the kernel reclaims it after this unmap and the
+        // host's release; there is no temp file to remove.
+        #[cfg(target_os = "linux")]
+        if let Some(ptr) = self.shm_ptr {
+            unsafe {
+                libc::munmap(ptr as *mut libc::c_void, self.shm_size);
+            }
+        }
+    }
+}
+
+impl PluginInstance for WineVst3Plugin {
+    fn initialize(&mut self, sample_rate: f64, max_block_size: usize) -> Result<()> {
+        self.sample_rate = sample_rate;
+        self.block_size = max_block_size;
+
+        // Ring layout: pipeline input blocks ahead of the host over a sealed
+        // memfd. Requires both fd passing and a ring depth configured.
+        #[cfg(target_os = "linux")]
+        if self.audio_ring_slots > 0
+            && self.host_caps & RACK_WINE_CAP_FD_PASSING != 0
+            && self.client.control.is_some()
+        {
+            self.setup_audio_ring(max_block_size, self.audio_ring_slots)?;
+            self.initialized = true;
+            return Ok(());
+        }
+
+        // The audio buffer is always handed to the host as a sealed memfd over
+        // SCM_RIGHTS; there is no cross-process-openable named path to fall
+        // back to (a `memfd`'s `/proc/self/fd/N` entry only resolves inside
+        // the process that owns the descriptor), so a host that doesn't
+        // advertise fd passing simply can't be wired up for audio.
+        #[cfg(target_os = "linux")]
+        if self.host_caps & RACK_WINE_CAP_FD_PASSING == 0 || self.client.control.is_none() {
+            return Err(Error::Other(
+                "Host does not support fd-passing shared memory".to_string(),
+            ));
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let fd = self.setup_shared_memory_memfd(max_block_size, self.num_inputs, self.num_outputs)?;
+            let result = self.client.init_audio_fd(
+                sample_rate as u32,
+                max_block_size as u32,
+                self.num_inputs as u32,
+                self.num_outputs as u32,
+                fd,
+            );
+            // The host holds its own dup after SCM_RIGHTS, so our copy is no
+            // longer needed; the mapping stays valid once the fd is closed.
+            unsafe { libc::close(fd) };
+            self.shm_fd = None;
+            result?;
+            self.shm_handshake = self.host_caps & RACK_WINE_CAP_SHM_HANDSHAKE != 0;
+            self.initialized = true;
+            return Ok(());
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        Err(Error::Other("Wine VST3 host only supported on Linux".to_string()))
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        // No explicit reset command in the protocol
+        // Could reinitialize if needed
+        Ok(())
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        num_frames: usize,
+    ) -> Result<()> {
+        let result = self.process_dispatch(inputs, outputs, num_frames);
+        // A failed block against a host that has exited is a crash: recover so
+        // the next block can proceed. This block is still reported as failed.
+        if result.is_err() && !self._server.is_alive() {
+            let _ = self.recover_from_crash();
+        }
+        // Drain any MIDI the plugin produced this block into the reusable buffer
+        // so MIDI effects and arpeggiators are visible through `output_midi`.
+        // `collect_output_midi` itself no-ops unless the caller opted in via
+        // `set_midi_out_enabled`, so this costs nothing for ordinary plugins.
+        if result.is_ok() {
+            self.collect_output_midi();
+        }
+        result
+    }
+
     fn parameter_count(&self) -> usize {
         self.param_ids.len()
     }
@@ -691,63 +1780,96 @@ impl PluginInstance for WineVst3Plugin {
             return Err(Error::InvalidParameter(index));
         }
         let param_id = self.param_ids[index];
-        self.client.set_param(param_id, value as f64)
+        self.client.set_param(param_id, value as f64)?;
+        // Remember the value so it can be replayed onto a respawned host.
+        self.param_cache.insert(param_id, value as f64);
+        Ok(())
     }
 
     fn send_midi(&mut self, events: &[MidiEvent]) -> Result<()> {
-        let midi_events: Vec<protocol::MidiEvent> = events.iter().map(|e| {
-            let (status, data1, data2) = match e.kind {
+        let mut midi_events: Vec<protocol::MidiEventBytes> = Vec::with_capacity(events.len());
+        for e in events {
+            // A tuning change with more than 127 retuned keys doesn't fit the
+            // SysEx's 7-bit count field, so it encodes to several messages;
+            // emit each as its own event at the same sample offset.
+            if let crate::MidiEventKind::SingleNoteTuning { program, changes } = &e.kind {
+                for msg in encode_mts_single_note_tuning(*program, changes) {
+                    midi_events.push(protocol::MidiEventBytes::new(e.sample_offset, msg));
+                }
+                continue;
+            }
+
+            // Channel-voice messages stay a compact 3-byte payload; SysEx keeps
+            // its full variable-length `F0 … F7` slice.
+            let bytes = match &e.kind {
                 crate::MidiEventKind::NoteOn { note, velocity, channel } => {
-                    (0x90 | (channel & 0x0F), note, velocity)
+                    vec![0x90 | (*channel & 0x0F), *note, *velocity]
                 }
                 crate::MidiEventKind::NoteOff { note, velocity, channel } => {
-                    (0x80 | (channel & 0x0F), note, velocity)
+                    vec![0x80 | (*channel & 0x0F), *note, *velocity]
                 }
                 crate::MidiEventKind::ControlChange { controller, value, channel } => {
-                    (0xB0 | (channel & 0x0F), controller, value)
+                    vec![0xB0 | (*channel & 0x0F), *controller, *value]
                 }
                 crate::MidiEventKind::ProgramChange { program, channel } => {
-                    (0xC0 | (channel & 0x0F), program, 0)
+                    vec![0xC0 | (*channel & 0x0F), *program, 0]
                 }
                 crate::MidiEventKind::PitchBend { value, channel } => {
-                    let lsb = (value & 0x7F) as u8;
-                    let msb = ((value >> 7) & 0x7F) as u8;
-                    (0xE0 | (channel & 0x0F), lsb, msb)
+                    let lsb = (*value & 0x7F) as u8;
+                    let msb = ((*value >> 7) & 0x7F) as u8;
+                    vec![0xE0 | (*channel & 0x0F), lsb, msb]
                 }
                 crate::MidiEventKind::PolyphonicAftertouch { note, pressure, channel } => {
-                    (0xA0 | (channel & 0x0F), note, pressure)
+                    vec![0xA0 | (*channel & 0x0F), *note, *pressure]
                 }
                 crate::MidiEventKind::ChannelAftertouch { pressure, channel } => {
-                    (0xD0 | (channel & 0x0F), pressure, 0)
+                    vec![0xD0 | (*channel & 0x0F), *pressure, 0]
                 }
-                _ => (0, 0, 0), // Ignore system real-time messages
+                crate::MidiEventKind::SysEx { data } => data.clone(),
+                _ => continue, // System real-time messages are not forwarded.
             };
-            protocol::MidiEvent::new(e.sample_offset, status, data1, data2)
-        }).collect();
+            midi_events.push(protocol::MidiEventBytes::new(e.sample_offset, bytes));
+        }
 
-        self.client.send_midi(&midi_events)
+        let running_status = self.host_caps & RACK_WINE_CAP_MIDI_RUNNING_STATUS != 0;
+        self.client.send_midi(&midi_events, running_status)
     }
 
     fn preset_count(&self) -> Result<usize> {
-        // Not implemented in protocol yet
-        Ok(0)
+        self.client.preset_count()
     }
 
-    fn preset_info(&self, _index: usize) -> Result<PresetInfo> {
-        Err(Error::Other("Presets not implemented".to_string()))
+    fn preset_info(&self, index: usize) -> Result<PresetInfo> {
+        let info = self.client.preset_info(index)?;
+        Ok(PresetInfo {
+            index: info.index as usize,
+            name: info.name,
+        })
     }
 
-    fn load_preset(&mut self, _preset_number: i32) -> Result<()> {
-        Err(Error::Other("Presets not implemented".to_string()))
+    fn load_preset(&mut self, preset_number: i32) -> Result<()> {
+        self.client.load_preset(preset_number)?;
+        // Selecting a program rewrites the plugin's parameters wholesale, so the
+        // replay cache is now stale; re-read the new values to keep a later
+        // respawn restoring this program rather than the old one.
+        let ids = self.param_ids.clone();
+        for id in ids {
+            if let Ok(value) = self.client.get_param(id) {
+                self.param_cache.insert(id, value);
+            }
+        }
+        Ok(())
     }
 
     fn get_state(&self) -> Result<Vec<u8>> {
-        // Not implemented in protocol yet
-        Err(Error::Other("State serialization not implemented".to_string()))
+        let blob = self.client.get_chunk(ChunkScope::Bank)?;
+        state_codec::decode(&blob)
     }
 
-    fn set_state(&mut self, _data: &[u8]) -> Result<()> {
-        Err(Error::Other("State serialization not implemented".to_string()))
+    fn set_state(&mut self, data: &[u8]) -> Result<()> {
+        let codec = state_codec::Codec::preferred(self.host_caps);
+        let blob = state_codec::encode(data, codec);
+        self.client.set_chunk(ChunkScope::Bank, &blob)
     }
 
     fn info(&self) -> &PluginInfo {
@@ -758,3 +1880,90 @@ impl PluginInstance for WineVst3Plugin {
         self.initialized
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mts_pitch_at_semitone_boundary() {
+        // Exactly on a semitone: zero fraction.
+        assert_eq!(encode_mts_pitch(60.0), (60, 0, 0));
+    }
+
+    #[test]
+    fn mts_pitch_at_fraction_boundary() {
+        // Half a semitone up: 8192/16384 units, split 7-bit MSB-first.
+        assert_eq!(encode_mts_pitch(60.5), (60, 0x40, 0x00));
+    }
+
+    #[test]
+    fn mts_pitch_clamps_above_range_to_no_change() {
+        assert_eq!(encode_mts_pitch(200.0), (127, 0x7F, 0x7F));
+    }
+
+    #[test]
+    fn mts_pitch_clamps_below_range() {
+        assert_eq!(encode_mts_pitch(-5.0), (0, 0, 0));
+    }
+
+    /// Decode a Single Note Tuning Change SysEx back into its `(program,
+    /// changes)` for round-trip assertions, mirroring the wire layout
+    /// `encode_mts_single_note_tuning` writes.
+    fn decode_mts_single_note_tuning(bytes: &[u8]) -> (u8, Vec<(u8, u8, u8, u8)>) {
+        assert_eq!(&bytes[..5], &[0xF0, 0x7F, MTS_DEVICE_ID_ALL, 0x08, 0x02]);
+        assert_eq!(*bytes.last().unwrap(), 0xF7);
+        let program = bytes[5];
+        let n = bytes[6] as usize;
+        let mut changes = Vec::with_capacity(n);
+        let mut off = 7;
+        for _ in 0..n {
+            changes.push((bytes[off], bytes[off + 1], bytes[off + 2], bytes[off + 3]));
+            off += 4;
+        }
+        assert_eq!(off, bytes.len() - 1);
+        (program, changes)
+    }
+
+    #[test]
+    fn mts_single_note_tuning_roundtrips() {
+        let changes = vec![(60, 60.0), (61, 60.5), (62, 127.0)];
+        let messages = encode_mts_single_note_tuning(3, &changes);
+        assert_eq!(messages.len(), 1);
+
+        let (program, decoded) = decode_mts_single_note_tuning(&messages[0]);
+        assert_eq!(program, 3);
+        assert_eq!(decoded.len(), changes.len());
+        for (&(key, pitch), &(dkey, xx, yy, zz)) in changes.iter().zip(&decoded) {
+            assert_eq!(dkey, key);
+            assert_eq!((xx, yy, zz), encode_mts_pitch(pitch));
+        }
+
+        // The whole message decodes as one SysEx event, the same path a
+        // received MTS message from the host would take.
+        match decode_midi_kind(&messages[0]) {
+            Some(crate::MidiEventKind::SysEx { data }) => assert_eq!(data, messages[0]),
+            _ => panic!("expected SysEx"),
+        }
+    }
+
+    #[test]
+    fn mts_single_note_tuning_splits_past_127_changes() {
+        // The `<n>` count is a single 7-bit byte, so 128 changes must become
+        // two messages (127 + 1) rather than wrapping the count to 0.
+        let changes: Vec<(u8, f64)> = (0..128).map(|k| (k as u8, k as f64)).collect();
+        let messages = encode_mts_single_note_tuning(0, &changes);
+        assert_eq!(messages.len(), 2);
+
+        let (_, first) = decode_mts_single_note_tuning(&messages[0]);
+        let (_, second) = decode_mts_single_note_tuning(&messages[1]);
+        assert_eq!(first.len(), 127);
+        assert_eq!(second.len(), 1);
+        assert_eq!(first.len() + second.len(), changes.len());
+
+        for (i, &(key, _)) in changes.iter().enumerate() {
+            let (dkey, ..) = if i < 127 { first[i] } else { second[i - 127] };
+            assert_eq!(dkey, key);
+        }
+    }
+}
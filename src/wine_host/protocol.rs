@@ -1,18 +1,54 @@
 //! Protocol definitions for Wine host communication
 //!
 //! This module mirrors the C protocol.h definitions for IPC with the Wine host.
+//!
+//! The wire structs are `zerocopy`-derived so a message can be parsed or
+//! serialized directly over a socket buffer with no manual index arithmetic:
+//! [`Header::read_from`] / [`Header::as_bytes`] replace the old copy loops, and
+//! the variable-length `Resp*` responses are decoded with a checked
+//! [`Ref::new_from_prefix`] over the byte slice instead of hardcoded offsets.
+//! `zerocopy` does not byte-swap, so every multi-byte field uses a little-endian
+//! wrapper (`U32<LittleEndian>`, `F64<LittleEndian>`); that keeps each
+//! `#[repr(C, packed)]` struct `Unaligned` while preserving the exact on-wire
+//! little-endian layout the C host expects.
+
+use zerocopy::byteorder::{LittleEndian, F64, I32, U32, U64};
+use zerocopy::{AsBytes, FromBytes, FromZeroes, Ref, Unaligned};
 
 /// Protocol magic numbers
 pub const RACK_WINE_MAGIC: u32 = 0x484E5752; // 'RWNH' in little-endian
 pub const RACK_WINE_RESPONSE_MAGIC: u32 = 0x524E5752; // 'RWNR'
 
 /// Protocol version
-pub const RACK_WINE_PROTOCOL_VERSION: u32 = 1;
+///
+/// Version 2 adds a `request_id` to [`Header`] and [`ResponseHeader`] so the
+/// transport can pipeline and correlate out-of-order responses. The codec's v1
+/// mode (see [`RACK_WINE_PROTOCOL_VERSION_V1`]) reads id-less v1 responses and
+/// matches them in FIFO order; requests are always written with the current
+/// [`Header`] layout, whatever the version.
+///
+/// Version 3 adds an `instance_id` to [`Header`] so a single host process can
+/// multiplex commands for many plugin instances over one connection. Responses
+/// still correlate by `request_id` alone, so [`ResponseHeader`] is unchanged.
+/// The extra field makes [`Header`] 24 bytes, so a v2 host, which reads a
+/// 20-byte header, does not parse v3 requests.
+pub const RACK_WINE_PROTOCOL_VERSION: u32 = 3;
+
+/// Legacy lock-step protocol version that carries no `request_id`.
+pub const RACK_WINE_PROTOCOL_VERSION_V1: u32 = 1;
 
 /// TCP port range for Wine host
 pub const RACK_WINE_PORT_BASE: u16 = 47100;
 pub const RACK_WINE_PORT_MAX: u16 = 47199;
 
+/// Stdout line on which the host announces the TCP port it bound, e.g.
+/// `RACK_WINE_PORT 47100`. Parsed at bootstrap instead of probing the range.
+pub const RACK_WINE_PORT_PREFIX: &str = "RACK_WINE_PORT";
+
+/// Stdout token the host prints once it is listening and ready to serve, so the
+/// client can connect the instant it appears rather than sleeping a fixed delay.
+pub const RACK_WINE_READY_TOKEN: &str = "RACK_WINE_READY";
+
 /// Command types (named HostCommand to avoid conflict with std::process::Command)
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,8 +69,36 @@ pub enum HostCommand {
     OpenEditor = 14,
     CloseEditor = 15,
     GetEditorSize = 16,
+    /// Open the editor in offscreen mode, rendering into a client-supplied
+    /// framebuffer passed as a descriptor (see [`CmdOpenEditorOffscreen`]).
+    OpenEditorOffscreen = 17,
+    /// Relay one mouse/keyboard event into an offscreen editor (see
+    /// [`CmdInputEvent`]).
+    SendInputEvent = 18,
+    /// Named-shm audio init (see [`CmdInitAudio`]). No longer issued by this
+    /// client — every host is required to advertise [`RACK_WINE_CAP_FD_PASSING`]
+    /// and use [`InitAudioFd`](HostCommand::InitAudioFd) instead — but the
+    /// command id and payload stay part of the wire protocol so it keeps its
+    /// slot rather than being reassigned.
+    #[allow(dead_code)]
     InitAudio = 20,
     ProcessAudio = 21,
+    /// Like [`InitAudio`](HostCommand::InitAudio) but the audio buffer is handed
+    /// over as a sealed `memfd` via `SCM_RIGHTS` rather than looked up by name.
+    InitAudioFd = 22,
+    /// Number of programs the loaded plugin exposes (see [`RespPresetCount`]).
+    GetPresetCount = 23,
+    /// Name and metadata for one program by index (see [`CmdPresetInfo`]).
+    GetPresetInfo = 24,
+    /// Select the plugin's current program (see [`CmdLoadPreset`]).
+    LoadPreset = 25,
+    /// Collect MIDI the plugin emitted during the last processed block. No
+    /// longer issued by this client — output MIDI instead rides the shm
+    /// [`ShmMidiOutHeader`] region the host fills alongside the audio buffers
+    /// — but the command id stays reserved for a host that only speaks the
+    /// older TCP-polled scheme.
+    #[allow(dead_code)]
+    GetMidiOut = 26,
     Shutdown = 99,
 }
 
@@ -64,110 +128,93 @@ impl From<u32> for Status {
 
 /// Message header (all messages start with this)
 #[repr(C, packed)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct Header {
-    pub magic: u32,
-    pub version: u32,
-    pub command: u32,
-    pub payload_size: u32,
+    pub magic: U32<LittleEndian>,
+    pub version: U32<LittleEndian>,
+    pub command: U32<LittleEndian>,
+    pub payload_size: U32<LittleEndian>,
+    /// Correlation id echoed back in the matching [`ResponseHeader`]; 0 on v1.
+    pub request_id: U32<LittleEndian>,
+    /// Plugin instance this command targets on a multiplexed host (added in
+    /// v3, see [`RACK_WINE_PROTOCOL_VERSION`]).
+    pub instance_id: U32<LittleEndian>,
 }
 
 impl Header {
-    pub fn new(command: HostCommand, payload_size: u32) -> Self {
-        Self {
-            magic: RACK_WINE_MAGIC,
-            version: RACK_WINE_PROTOCOL_VERSION,
-            command: command as u32,
-            payload_size,
-        }
-    }
-
-    pub fn to_bytes(&self) -> [u8; 16] {
-        let mut buf = [0u8; 16];
-        buf[0..4].copy_from_slice(&self.magic.to_le_bytes());
-        buf[4..8].copy_from_slice(&self.version.to_le_bytes());
-        buf[8..12].copy_from_slice(&self.command.to_le_bytes());
-        buf[12..16].copy_from_slice(&self.payload_size.to_le_bytes());
-        buf
-    }
-
-    pub fn from_bytes(buf: &[u8; 16]) -> Self {
+    pub fn new(command: HostCommand, payload_size: u32, request_id: u32, instance_id: u32) -> Self {
         Self {
-            magic: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
-            version: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
-            command: u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]),
-            payload_size: u32::from_le_bytes([buf[12], buf[13], buf[14], buf[15]]),
+            magic: U32::new(RACK_WINE_MAGIC),
+            version: U32::new(RACK_WINE_PROTOCOL_VERSION),
+            command: U32::new(command as u32),
+            payload_size: U32::new(payload_size),
+            request_id: U32::new(request_id),
+            instance_id: U32::new(instance_id),
         }
     }
 }
 
 /// Response header
 #[repr(C, packed)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct ResponseHeader {
-    pub magic: u32,
-    pub status: u32,
-    pub payload_size: u32,
+    pub magic: U32<LittleEndian>,
+    pub status: U32<LittleEndian>,
+    pub payload_size: U32<LittleEndian>,
+    /// Correlation id copied from the request's [`Header`]; 0 on v1.
+    pub request_id: U32<LittleEndian>,
 }
 
 impl ResponseHeader {
-    pub fn from_bytes(buf: &[u8; 12]) -> Self {
-        Self {
-            magic: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
-            status: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
-            payload_size: u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]),
-        }
-    }
-
     pub fn status(&self) -> Status {
-        Status::from(self.status)
+        Status::from(self.status.get())
     }
 }
 
 /// CMD_LOAD_PLUGIN payload
 #[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct CmdLoadPlugin {
     pub path: [u8; 1024],
-    pub class_index: u32,
+    pub class_index: U32<LittleEndian>,
 }
 
 impl CmdLoadPlugin {
     pub fn new(path: &str, class_index: u32) -> Self {
         let mut cmd = Self {
             path: [0u8; 1024],
-            class_index,
+            class_index: U32::new(class_index),
         };
         let bytes = path.as_bytes();
         let len = bytes.len().min(1023);
         cmd.path[..len].copy_from_slice(&bytes[..len]);
         cmd
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(1028);
-        buf.extend_from_slice(&self.path);
-        buf.extend_from_slice(&self.class_index.to_le_bytes());
-        buf
-    }
 }
 
 /// CMD_INIT_AUDIO payload
+///
+/// Kept for wire compatibility with [`HostCommand::InitAudio`] even though
+/// this client no longer constructs one — see that variant's doc comment.
 #[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+#[allow(dead_code)]
 pub struct CmdInitAudio {
-    pub sample_rate: u32,
-    pub block_size: u32,
-    pub num_inputs: u32,
-    pub num_outputs: u32,
+    pub sample_rate: U32<LittleEndian>,
+    pub block_size: U32<LittleEndian>,
+    pub num_inputs: U32<LittleEndian>,
+    pub num_outputs: U32<LittleEndian>,
     pub shm_name: [u8; 64],
 }
 
 impl CmdInitAudio {
+    #[allow(dead_code)]
     pub fn new(sample_rate: u32, block_size: u32, num_inputs: u32, num_outputs: u32, shm_name: &str) -> Self {
         let mut cmd = Self {
-            sample_rate,
-            block_size,
-            num_inputs,
-            num_outputs,
+            sample_rate: U32::new(sample_rate),
+            block_size: U32::new(block_size),
+            num_inputs: U32::new(num_inputs),
+            num_outputs: U32::new(num_outputs),
             shm_name: [0u8; 64],
         };
         let bytes = shm_name.as_bytes();
@@ -175,52 +222,157 @@ impl CmdInitAudio {
         cmd.shm_name[..len].copy_from_slice(&bytes[..len]);
         cmd
     }
+}
+
+/// Capability flags advertised by the host in the `GetInfo` response `flags`.
+///
+/// When [`RACK_WINE_CAP_FD_PASSING`] is set the host can receive the audio
+/// buffer as a sealed `memfd` passed over an `AF_UNIX` socket with `SCM_RIGHTS`,
+/// so the client can use [`CmdInitAudioFd`] instead of the named-shm
+/// [`CmdInitAudio`]. Hosts without the bit only support the name-based path.
+pub const RACK_WINE_CAP_FD_PASSING: u32 = 1 << 0;
+
+/// Host understands zstd-compressed `GetState`/`SetState` payloads (see
+/// [`state_codec`](super::state_codec)). The client only emits a compressed
+/// `SetState` blob when the matching capability bit is advertised here.
+pub const RACK_WINE_CAP_COMPRESS_ZSTD: u32 = 1 << 1;
+
+/// Host understands LZMA-compressed `GetState`/`SetState` payloads.
+pub const RACK_WINE_CAP_COMPRESS_LZMA: u32 = 1 << 2;
+
+/// Which slice of plugin state a `GetState`/`SetState` round trip covers,
+/// mirroring the VST 2.4 split between the whole bank (`isPreset = false`) and
+/// the current program alone (`isPreset = true`).
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkScope {
+    /// The entire plugin: every program plus any global state.
+    Bank = 0,
+    /// Only the currently selected program.
+    Program = 1,
+}
+
+/// Scope selector prefixing a `GetState`/`SetState` payload.
+///
+/// `GetState` carries just this word; `SetState` carries it followed by the
+/// state blob the host should restore.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct CmdChunkScope {
+    pub scope: U32<LittleEndian>,
+}
+
+impl CmdChunkScope {
+    pub const SIZE: usize = std::mem::size_of::<Self>();
+
+    pub fn new(scope: ChunkScope) -> Self {
+        Self {
+            scope: U32::new(scope as u32),
+        }
+    }
+}
+
+/// Host mirrors the `host_ready`/`client_ready` flag handshake in shared memory,
+/// so `process` can drive a block through the shared page instead of a TCP
+/// `ProcessAudio` round trip. Hosts without the bit only support the TCP path.
+pub const RACK_WINE_CAP_SHM_HANDSHAKE: u32 = 1 << 3;
+
+/// Host understands running-status-compressed `SendMidi` batches, where a
+/// channel-voice event that repeats the previous status byte omits it (see
+/// [`encode_midi_batch`]). Hosts without the bit only accept fully-framed
+/// events.
+pub const RACK_WINE_CAP_MIDI_RUNNING_STATUS: u32 = 1 << 5;
+
+/// Host can render the editor offscreen into a client-supplied framebuffer and
+/// accept relayed input events, so a Wayland or headless caller can composite
+/// the GUI without an X11 window (see [`CmdOpenEditorOffscreen`]). Hosts without
+/// the bit only support the `OpenEditor` X11-embedding path.
+pub const RACK_WINE_CAP_OFFSCREEN_EDITOR: u32 = 1 << 4;
+
+/// Host writes the plugin's output MIDI for the last processed block into the
+/// [`ShmMidiOutHeader`] region trailing the audio buffers (see
+/// [`ShmHeader::midi_out_offset`]), rather than requiring a separate RPC to
+/// fetch it. The client only reads the region for instances that opt in (see
+/// [`WineVst3Plugin::set_midi_out_enabled`](super::WineVst3Plugin::set_midi_out_enabled))
+/// — plugins that never emit MIDI pay nothing, even on a host that advertises
+/// the bit. Hosts without the bit never populate the region.
+pub const RACK_WINE_CAP_MIDI_OUT: u32 = 1 << 6;
+
+/// CMD_INIT_AUDIO_FD payload
+///
+/// Carries the same audio configuration as [`CmdInitAudio`] but without the
+/// `shm_name`: the buffer is identified by the file descriptor sent alongside
+/// this message as ancillary data.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct CmdInitAudioFd {
+    pub sample_rate: U32<LittleEndian>,
+    pub block_size: U32<LittleEndian>,
+    pub num_inputs: U32<LittleEndian>,
+    pub num_outputs: U32<LittleEndian>,
+}
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(80);
-        buf.extend_from_slice(&self.sample_rate.to_le_bytes());
-        buf.extend_from_slice(&self.block_size.to_le_bytes());
-        buf.extend_from_slice(&self.num_inputs.to_le_bytes());
-        buf.extend_from_slice(&self.num_outputs.to_le_bytes());
-        buf.extend_from_slice(&self.shm_name);
-        buf
+impl CmdInitAudioFd {
+    pub fn new(sample_rate: u32, block_size: u32, num_inputs: u32, num_outputs: u32) -> Self {
+        Self {
+            sample_rate: U32::new(sample_rate),
+            block_size: U32::new(block_size),
+            num_inputs: U32::new(num_inputs),
+            num_outputs: U32::new(num_outputs),
+        }
     }
 }
 
 /// CMD_PROCESS_AUDIO payload
 #[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct CmdProcessAudio {
-    pub num_samples: u32,
+    pub num_samples: U32<LittleEndian>,
 }
 
 impl CmdProcessAudio {
     pub fn new(num_samples: u32) -> Self {
-        Self { num_samples }
-    }
-
-    pub fn to_bytes(&self) -> [u8; 4] {
-        self.num_samples.to_le_bytes()
+        Self {
+            num_samples: U32::new(num_samples),
+        }
     }
 }
 
 /// CMD_GET_PARAM / CMD_SET_PARAM payload
 #[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct CmdParam {
-    pub param_id: u32,
-    pub value: f64,
+    pub param_id: U32<LittleEndian>,
+    pub value: F64<LittleEndian>,
 }
 
 impl CmdParam {
     pub fn new(param_id: u32, value: f64) -> Self {
-        Self { param_id, value }
+        Self {
+            param_id: U32::new(param_id),
+            value: F64::new(value),
+        }
     }
+}
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(12);
-        buf.extend_from_slice(&self.param_id.to_le_bytes());
-        buf.extend_from_slice(&self.value.to_le_bytes());
-        buf
-    }
+/// Read a NUL-terminated, fixed-capacity name field into an owned `String`.
+fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).to_string()
+}
+
+/// On-wire layout of a plugin info response.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, Unaligned)]
+struct WirePluginInfo {
+    name: [u8; 256],
+    vendor: [u8; 256],
+    category: [u8; 128],
+    uid: [u8; 64],
+    num_params: U32<LittleEndian>,
+    num_audio_inputs: U32<LittleEndian>,
+    num_audio_outputs: U32<LittleEndian>,
+    flags: U32<LittleEndian>,
 }
 
 /// Response: Plugin info
@@ -238,32 +390,33 @@ pub struct RespPluginInfo {
 
 impl RespPluginInfo {
     pub fn from_bytes(buf: &[u8]) -> Option<Self> {
-        if buf.len() < 716 {
-            return None;
-        }
-
-        fn read_string(data: &[u8], max_len: usize) -> String {
-            let end = data.iter().position(|&b| b == 0).unwrap_or(max_len);
-            String::from_utf8_lossy(&data[..end]).to_string()
-        }
-
+        let (wire, _) = Ref::<_, WirePluginInfo>::new_from_prefix(buf)?;
         Some(Self {
-            name: read_string(&buf[0..256], 256),
-            vendor: read_string(&buf[256..512], 256),
-            category: read_string(&buf[512..640], 128),
-            uid: read_string(&buf[640..704], 64),
-            num_params: u32::from_le_bytes([buf[704], buf[705], buf[706], buf[707]]),
-            num_audio_inputs: u32::from_le_bytes([buf[708], buf[709], buf[710], buf[711]]),
-            num_audio_outputs: u32::from_le_bytes([buf[712], buf[713], buf[714], buf[715]]),
-            flags: if buf.len() >= 720 {
-                u32::from_le_bytes([buf[716], buf[717], buf[718], buf[719]])
-            } else {
-                0
-            },
+            name: read_string(&wire.name),
+            vendor: read_string(&wire.vendor),
+            category: read_string(&wire.category),
+            uid: read_string(&wire.uid),
+            num_params: wire.num_params.get(),
+            num_audio_inputs: wire.num_audio_inputs.get(),
+            num_audio_outputs: wire.num_audio_outputs.get(),
+            flags: wire.flags.get(),
         })
     }
 }
 
+/// On-wire layout of a parameter info response.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, Unaligned)]
+struct WireParamInfo {
+    id: U32<LittleEndian>,
+    name: [u8; 128],
+    units: [u8; 32],
+    default_value: F64<LittleEndian>,
+    min_value: F64<LittleEndian>,
+    max_value: F64<LittleEndian>,
+    flags: U32<LittleEndian>,
+}
+
 /// Response: Parameter info
 #[derive(Debug, Clone)]
 pub struct RespParamInfo {
@@ -278,36 +431,28 @@ pub struct RespParamInfo {
 
 impl RespParamInfo {
     pub fn from_bytes(buf: &[u8]) -> Option<Self> {
-        if buf.len() < 196 {
-            return None;
-        }
-
-        fn read_string(data: &[u8], max_len: usize) -> String {
-            let end = data.iter().position(|&b| b == 0).unwrap_or(max_len);
-            String::from_utf8_lossy(&data[..end]).to_string()
-        }
-
+        let (wire, _) = Ref::<_, WireParamInfo>::new_from_prefix(buf)?;
         Some(Self {
-            id: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
-            name: read_string(&buf[4..132], 128),
-            units: read_string(&buf[132..164], 32),
-            default_value: f64::from_le_bytes([
-                buf[164], buf[165], buf[166], buf[167],
-                buf[168], buf[169], buf[170], buf[171],
-            ]),
-            min_value: f64::from_le_bytes([
-                buf[172], buf[173], buf[174], buf[175],
-                buf[176], buf[177], buf[178], buf[179],
-            ]),
-            max_value: f64::from_le_bytes([
-                buf[180], buf[181], buf[182], buf[183],
-                buf[184], buf[185], buf[186], buf[187],
-            ]),
-            flags: u32::from_le_bytes([buf[188], buf[189], buf[190], buf[191]]),
+            id: wire.id.get(),
+            name: read_string(&wire.name),
+            units: read_string(&wire.units),
+            default_value: wire.default_value.get(),
+            min_value: wire.min_value.get(),
+            max_value: wire.max_value.get(),
+            flags: wire.flags.get(),
         })
     }
 }
 
+/// On-wire layout of an editor info response.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, Unaligned)]
+struct WireEditorInfo {
+    x11_window_id: U32<LittleEndian>,
+    width: U32<LittleEndian>,
+    height: U32<LittleEndian>,
+}
+
 /// Response: Editor info
 #[derive(Debug, Clone, Default)]
 pub struct RespEditorInfo {
@@ -318,66 +463,359 @@ pub struct RespEditorInfo {
 
 impl RespEditorInfo {
     pub fn from_bytes(buf: &[u8]) -> Option<Self> {
-        if buf.len() < 12 {
-            return None;
-        }
+        let (wire, _) = Ref::<_, WireEditorInfo>::new_from_prefix(buf)?;
         Some(Self {
-            x11_window_id: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
-            width: u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]),
-            height: u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]),
+            x11_window_id: wire.x11_window_id.get(),
+            width: wire.width.get(),
+            height: wire.height.get(),
         })
     }
 }
 
+/// CMD_OPEN_EDITOR_OFFSCREEN payload
+///
+/// Describes the geometry of the RGBA framebuffer the client has allocated and
+/// is passing alongside this message as a descriptor. The host maps the buffer,
+/// lays out a [`FbHeader`] at its start, and renders each dirtied frame into the
+/// pixel region that follows.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct CmdOpenEditorOffscreen {
+    pub width: U32<LittleEndian>,
+    pub height: U32<LittleEndian>,
+    pub stride: U32<LittleEndian>,
+}
+
+impl CmdOpenEditorOffscreen {
+    pub fn new(width: u32, height: u32, stride: u32) -> Self {
+        Self {
+            width: U32::new(width),
+            height: U32::new(height),
+            stride: U32::new(stride),
+        }
+    }
+}
+
+/// Kinds of input event relayed to an offscreen editor via [`CmdInputEvent`].
+pub const RACK_WINE_INPUT_MOUSE_MOVE: u32 = 0;
+pub const RACK_WINE_INPUT_MOUSE_DOWN: u32 = 1;
+pub const RACK_WINE_INPUT_MOUSE_UP: u32 = 2;
+pub const RACK_WINE_INPUT_MOUSE_WHEEL: u32 = 3;
+pub const RACK_WINE_INPUT_KEY_DOWN: u32 = 4;
+pub const RACK_WINE_INPUT_KEY_UP: u32 = 5;
+
+/// CMD_SEND_INPUT_EVENT payload
+///
+/// Carries one pointer or key event to inject into the offscreen editor. `code`
+/// holds the mouse button or virtual key depending on `kind`; `x`/`y` are in
+/// framebuffer pixels; `value` carries the wheel delta for a scroll event.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct CmdInputEvent {
+    pub kind: U32<LittleEndian>,
+    pub code: U32<LittleEndian>,
+    pub x: U32<LittleEndian>,
+    pub y: U32<LittleEndian>,
+    pub modifiers: U32<LittleEndian>,
+    pub value: F64<LittleEndian>,
+}
+
+impl CmdInputEvent {
+    pub fn new(kind: u32, code: u32, x: u32, y: u32, modifiers: u32, value: f64) -> Self {
+        Self {
+            kind: U32::new(kind),
+            code: U32::new(code),
+            x: U32::new(x),
+            y: U32::new(y),
+            modifiers: U32::new(modifiers),
+            value: F64::new(value),
+        }
+    }
+}
+
 /// MIDI event for sending to host
 #[repr(C, packed)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct MidiEvent {
-    pub sample_offset: u32,
+    pub sample_offset: U32<LittleEndian>,
     pub data: [u8; 4],
 }
 
 impl MidiEvent {
     pub fn new(sample_offset: u32, status: u8, data1: u8, data2: u8) -> Self {
         Self {
-            sample_offset,
+            sample_offset: U32::new(sample_offset),
             data: [status, data1, data2, 0],
         }
     }
+}
+
+/// Fixed-size header prefixing each variable-length MIDI event on the wire.
+///
+/// The fixed [`MidiEvent`] assumes exactly three data bytes, so the catch-all
+/// conversion had to drop SysEx and multi-byte system messages. A
+/// `MidiEventHeader` instead frames a `sample_offset` and an explicit `length`,
+/// followed by that many raw status bytes, so the reader consumes a var-len
+/// slice — the full `F0 … F7` for a SysEx dump — rather than truncating to
+/// three. Channel-voice messages still carry a 3-byte payload, keeping the
+/// compact encoding.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct MidiEventHeader {
+    pub sample_offset: U32<LittleEndian>,
+    pub length: U32<LittleEndian>,
+}
+
+impl MidiEventHeader {
+    pub const SIZE: usize = std::mem::size_of::<Self>();
+
+    pub fn new(sample_offset: u32, length: u32) -> Self {
+        Self {
+            sample_offset: U32::new(sample_offset),
+            length: U32::new(length),
+        }
+    }
+}
+
+/// A variable-length MIDI event to send to the host.
+///
+/// Unlike [`MidiEvent`], the payload is an arbitrary byte slice, so SysEx and
+/// multi-byte system messages survive the trip intact. Serialized as a
+/// [`MidiEventHeader`] immediately followed by its `bytes`.
+pub struct MidiEventBytes {
+    pub sample_offset: u32,
+    pub bytes: Vec<u8>,
+}
+
+impl MidiEventBytes {
+    pub fn new(sample_offset: u32, bytes: Vec<u8>) -> Self {
+        Self { sample_offset, bytes }
+    }
 
-    pub fn to_bytes(&self) -> [u8; 8] {
-        let mut buf = [0u8; 8];
-        buf[0..4].copy_from_slice(&self.sample_offset.to_le_bytes());
-        buf[4..8].copy_from_slice(&self.data);
-        buf
+    /// Bytes this event occupies on the wire (header plus payload).
+    pub fn wire_len(&self) -> usize {
+        MidiEventHeader::SIZE + self.bytes.len()
+    }
+
+    /// Append the header and payload to `out`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let header = MidiEventHeader::new(self.sample_offset, self.bytes.len() as u32);
+        out.extend_from_slice(header.as_bytes());
+        out.extend_from_slice(&self.bytes);
     }
 }
 
 /// CMD_SEND_MIDI header
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct CmdMidi {
-    pub num_events: u32,
+    pub num_events: U32<LittleEndian>,
 }
 
 impl CmdMidi {
-    pub fn to_bytes(&self) -> [u8; 4] {
-        self.num_events.to_le_bytes()
+    pub fn new(num_events: u32) -> Self {
+        Self {
+            num_events: U32::new(num_events),
+        }
+    }
+}
+
+/// Serialize a batch of [`MidiEventBytes`] as a `SendMidi` payload: a [`CmdMidi`]
+/// header followed by one framed event each.
+///
+/// With `running_status` set, the batch is first sorted by sample offset and
+/// then compressed the way standard MIDI files track a `running_status`: a
+/// channel-voice event that repeats the previous status byte transmits only its
+/// data bytes, and any SysEx or system message emits its full bytes and resets
+/// the running status. [`decode_midi_batch`] restores every event's offset and
+/// full bytes, in encoded order (sorted by sample offset with `running_status`).
+pub fn encode_midi_batch(events: &[MidiEventBytes], running_status: bool) -> Vec<u8> {
+    let body_len: usize = events.iter().map(|e| e.wire_len()).sum();
+    let mut payload = Vec::with_capacity(CmdMidi::new(0).as_bytes().len() + body_len);
+    payload.extend_from_slice(CmdMidi::new(events.len() as u32).as_bytes());
+
+    if !running_status {
+        for event in events {
+            event.encode(&mut payload);
+        }
+        return payload;
+    }
+
+    // Sort by sample offset so "previous status" follows playback order; the
+    // per-event header still carries the offset, so ordering stays lossless.
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by_key(|&i| events[i].sample_offset);
+
+    let mut last_status: Option<u8> = None;
+    for &i in &order {
+        let event = &events[i];
+        let emit: &[u8] = match event.bytes.first().copied() {
+            Some(status) if status >= 0xF0 => {
+                last_status = None;
+                &event.bytes
+            }
+            Some(status) if Some(status) == last_status => &event.bytes[1..],
+            Some(status) => {
+                last_status = Some(status);
+                &event.bytes
+            }
+            None => &event.bytes,
+        };
+        let header = MidiEventHeader::new(event.sample_offset, emit.len() as u32);
+        payload.extend_from_slice(header.as_bytes());
+        payload.extend_from_slice(emit);
+    }
+    payload
+}
+
+/// Decode a `SendMidi` payload produced by [`encode_midi_batch`], restoring any
+/// status bytes elided by running-status compression. Returns `None` if the
+/// payload is truncated or a data-only event arrives with no running status.
+pub fn decode_midi_batch(payload: &[u8]) -> Option<Vec<MidiEventBytes>> {
+    let (head, mut rest) = Ref::<_, CmdMidi>::new_from_prefix(payload)?;
+    let num_events = head.num_events.get() as usize;
+
+    let mut events = Vec::with_capacity(num_events);
+    let mut last_status: Option<u8> = None;
+    for _ in 0..num_events {
+        let (hdr, body) = Ref::<_, MidiEventHeader>::new_from_prefix(rest)?;
+        let len = hdr.length.get() as usize;
+        if body.len() < len {
+            return None;
+        }
+        let (raw, tail) = body.split_at(len);
+        rest = tail;
+
+        let bytes = match raw.first().copied() {
+            Some(status) if status >= 0x80 => {
+                last_status = if status >= 0xF0 { None } else { Some(status) };
+                raw.to_vec()
+            }
+            Some(_) => {
+                // Data byte first: the status was elided, prepend the running one.
+                let status = last_status?;
+                let mut full = Vec::with_capacity(len + 1);
+                full.push(status);
+                full.extend_from_slice(raw);
+                full
+            }
+            None => Vec::new(),
+        };
+        events.push(MidiEventBytes::new(hdr.sample_offset.get(), bytes));
+    }
+    Some(events)
+}
+
+/// Maximum program-name length on the wire, matching the 24-character cap the
+/// VST 2.4 `effGetProgramName` convention imposes.
+pub const RACK_WINE_PRESET_NAME_LEN: usize = 24;
+
+/// CMD_GET_PRESET_INFO payload: the program index to describe.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct CmdPresetInfo {
+    pub index: U32<LittleEndian>,
+}
+
+impl CmdPresetInfo {
+    pub fn new(index: u32) -> Self {
+        Self {
+            index: U32::new(index),
+        }
     }
 }
 
+/// CMD_LOAD_PRESET payload: the program number to select.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct CmdLoadPreset {
+    pub preset_number: I32<LittleEndian>,
+}
+
+impl CmdLoadPreset {
+    pub fn new(preset_number: i32) -> Self {
+        Self {
+            preset_number: I32::new(preset_number),
+        }
+    }
+}
+
+/// Response: number of programs the plugin exposes.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct RespPresetCount {
+    pub count: U32<LittleEndian>,
+}
+
+/// On-wire layout of a preset info response.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, Unaligned)]
+struct WirePresetInfo {
+    index: U32<LittleEndian>,
+    name: [u8; RACK_WINE_PRESET_NAME_LEN],
+}
+
+/// Response: one program's index and name.
+#[derive(Debug, Clone, Default)]
+pub struct RespPresetInfo {
+    pub index: u32,
+    pub name: String,
+}
+
+impl RespPresetInfo {
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        let (wire, _) = Ref::<_, WirePresetInfo>::new_from_prefix(buf)?;
+        Some(Self {
+            index: wire.index.get(),
+            name: read_string(&wire.name),
+        })
+    }
+}
+
+/// Shared-memory layout version: single `host_ready`/`client_ready` flags, one
+/// in-flight block at a time (the original lock-step handshake).
+pub const RACK_WINE_SHM_VERSION_FLAGS: u32 = 1;
+
+/// Shared-memory layout version: the SPSC slot ring described by `num_slots`,
+/// `slot_stride`, `write_index`, and `read_index`.
+pub const RACK_WINE_SHM_VERSION_RING: u32 = 2;
+
 /// Shared memory header
+///
+/// The `host_ready`/`client_ready` flags drive the v1 ([`RACK_WINE_SHM_VERSION_FLAGS`])
+/// lock-step handshake and are kept for compatibility. When `version` is
+/// [`RACK_WINE_SHM_VERSION_RING`] the region is instead an SPSC ring of
+/// `num_slots` fixed-`slot_stride` slots: the producer writes a slot then
+/// publishes it with a release increment of `write_index`, and the consumer
+/// acquires by observing `write_index > read_index`, both parties parking on
+/// the index word with `FUTEX_WAIT` rather than busy-polling the flags.
 #[repr(C)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct ShmHeader {
-    pub magic: u32,
-    pub version: u32,
-    pub num_inputs: u32,
-    pub num_outputs: u32,
-    pub block_size: u32,
-    pub sample_rate: u32,
-    pub host_ready: u32,
-    pub client_ready: u32,
-    pub input_offset: u32,
-    pub output_offset: u32,
-    pub reserved: [u32; 4],
+    pub magic: U32<LittleEndian>,
+    pub version: U32<LittleEndian>,
+    pub num_inputs: U32<LittleEndian>,
+    pub num_outputs: U32<LittleEndian>,
+    pub block_size: U32<LittleEndian>,
+    pub sample_rate: U32<LittleEndian>,
+    pub host_ready: U32<LittleEndian>,
+    pub client_ready: U32<LittleEndian>,
+    pub input_offset: U32<LittleEndian>,
+    pub output_offset: U32<LittleEndian>,
+    /// Number of ring slots (0 on the v1 flag layout).
+    pub num_slots: U32<LittleEndian>,
+    /// Byte stride between consecutive ring slots, including the per-slot header.
+    pub slot_stride: U32<LittleEndian>,
+    /// Monotonic count of slots published by the producer (release-stored).
+    pub write_index: U64<LittleEndian>,
+    /// Monotonic count of slots consumed by the consumer (release-stored).
+    pub read_index: U64<LittleEndian>,
+    /// Frame count the producer published for the current flag-handshake block.
+    pub process_frames: U32<LittleEndian>,
+    /// Byte offset of the [`ShmMidiOutHeader`] region trailing the audio
+    /// buffers, 0 if this mapping has no MIDI-out region (e.g. the ring
+    /// layout, which does not carry one yet).
+    pub midi_out_offset: U32<LittleEndian>,
 }
 
 pub const RACK_WINE_SHM_MAGIC: u32 = 0x52574153; // 'RWAS'
@@ -385,3 +823,149 @@ pub const RACK_WINE_SHM_MAGIC: u32 = 0x52574153; // 'RWAS'
 impl ShmHeader {
     pub const SIZE: usize = std::mem::size_of::<Self>();
 }
+
+/// Fixed byte budget of the MIDI-out region the host writes its produced
+/// events into each block, trailing the audio buffers at
+/// [`ShmHeader::midi_out_offset`].
+///
+/// Large enough for a burst of ordinary channel-voice events or a handful of
+/// SysEx dumps; a block that would overflow it sets
+/// [`ShmMidiOutHeader::overflow`] instead of growing past the region, the same
+/// way [`MIDI_OUT_CAPACITY`](super::MIDI_OUT_CAPACITY) caps the client-side
+/// buffer.
+pub const SHM_MIDI_OUT_REGION_BYTES: usize = 8192;
+
+/// Header prefixing the MIDI-out region: an event count plus an overflow
+/// flag, immediately followed by `num_events` events each framed as a
+/// [`MidiEventHeader`] plus its raw bytes (no running-status compression —
+/// the host writes this region directly rather than encoding a reply).
+#[repr(C)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct ShmMidiOutHeader {
+    pub num_events: U32<LittleEndian>,
+    /// Non-zero if the block produced more events than fit in
+    /// [`SHM_MIDI_OUT_REGION_BYTES`]; the surplus was dropped.
+    pub overflow: U32<LittleEndian>,
+}
+
+impl ShmMidiOutHeader {
+    pub const SIZE: usize = std::mem::size_of::<Self>();
+}
+
+/// Per-slot header that prefixes each ring slot's audio payload.
+#[repr(C)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct ShmSlotHeader {
+    /// Number of valid sample frames in this slot.
+    pub num_samples: U32<LittleEndian>,
+    pub reserved: U32<LittleEndian>,
+}
+
+impl ShmSlotHeader {
+    pub const SIZE: usize = std::mem::size_of::<Self>();
+}
+
+pub const RACK_WINE_FB_MAGIC: u32 = 0x52574642; // 'RWFB'
+
+/// Header prefixing the offscreen editor framebuffer region.
+///
+/// The pixel data (tightly packed RGBA rows of `stride` bytes) follows this
+/// header. The host GUI thread is the producer: it bumps `sequence` to an odd
+/// value before touching the pixels, writes the dirtied `damage_*` rectangle,
+/// then stores an even `sequence`. A compositor reads `sequence` (even), blits,
+/// and re-reads it; an unchanged even value means the frame did not tear — a
+/// seqlock, using the same acquire/release ordering the audio ring uses on its
+/// index words.
+#[repr(C)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct FbHeader {
+    pub magic: U32<LittleEndian>,
+    pub width: U32<LittleEndian>,
+    pub height: U32<LittleEndian>,
+    pub stride: U32<LittleEndian>,
+    /// Seqlock counter: odd while the producer writes, even once a frame is
+    /// published. Also serves as the monotonic frame counter.
+    pub sequence: U32<LittleEndian>,
+    pub damage_x: U32<LittleEndian>,
+    pub damage_y: U32<LittleEndian>,
+    pub damage_w: U32<LittleEndian>,
+    pub damage_h: U32<LittleEndian>,
+    pub reserved: U32<LittleEndian>,
+}
+
+impl FbHeader {
+    pub const SIZE: usize = std::mem::size_of::<Self>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ev(sample_offset: u32, bytes: &[u8]) -> MidiEventBytes {
+        MidiEventBytes::new(sample_offset, bytes.to_vec())
+    }
+
+    #[test]
+    fn midi_batch_roundtrips_without_running_status() {
+        let events = vec![
+            ev(0, &[0x90, 60, 100]),
+            ev(10, &[0x90, 64, 100]),
+            ev(20, &[0xF0, 0x7E, 0x7F, 0xF7]),
+        ];
+        let payload = encode_midi_batch(&events, false);
+        let decoded = decode_midi_batch(&payload).unwrap();
+        assert_eq!(decoded.len(), events.len());
+        for (d, e) in decoded.iter().zip(&events) {
+            assert_eq!(d.sample_offset, e.sample_offset);
+            assert_eq!(d.bytes, e.bytes);
+        }
+    }
+
+    #[test]
+    fn midi_batch_roundtrips_with_running_status_and_resets_on_sysex() {
+        // Two note-ons share a status byte, a SysEx interrupts (forcing a full
+        // status byte), then a further note-on must re-send its status rather
+        // than treating the SysEx's F0 as "previous status".
+        let events = vec![
+            ev(0, &[0x90, 60, 100]),
+            ev(10, &[0x90, 64, 110]),
+            ev(20, &[0xF0, 0x7E, 0x7F, 0xF7]),
+            ev(30, &[0x90, 67, 120]),
+        ];
+        let payload = encode_midi_batch(&events, true);
+        let decoded = decode_midi_batch(&payload).unwrap();
+        assert_eq!(decoded.len(), events.len());
+        for (d, e) in decoded.iter().zip(&events) {
+            assert_eq!(d.sample_offset, e.sample_offset);
+            assert_eq!(d.bytes, e.bytes);
+        }
+    }
+
+    #[test]
+    fn midi_batch_running_status_reorders_by_sample_offset() {
+        // Encoded out of order; running-status compression sorts by offset
+        // internally, but decode must still hand back every original event.
+        let events = vec![
+            ev(20, &[0x90, 67, 120]),
+            ev(0, &[0x90, 60, 100]),
+            ev(10, &[0x90, 64, 110]),
+        ];
+        let payload = encode_midi_batch(&events, true);
+        let mut decoded = decode_midi_batch(&payload).unwrap();
+        decoded.sort_by_key(|e| e.sample_offset);
+
+        assert_eq!(decoded[0].sample_offset, 0);
+        assert_eq!(decoded[0].bytes, vec![0x90, 60, 100]);
+        assert_eq!(decoded[1].sample_offset, 10);
+        assert_eq!(decoded[1].bytes, vec![0x90, 64, 110]);
+        assert_eq!(decoded[2].sample_offset, 20);
+        assert_eq!(decoded[2].bytes, vec![0x90, 67, 120]);
+    }
+
+    #[test]
+    fn decode_midi_batch_rejects_truncated_payload() {
+        let events = vec![ev(0, &[0x90, 60, 100])];
+        let payload = encode_midi_batch(&events, false);
+        assert!(decode_midi_batch(&payload[..payload.len() - 1]).is_none());
+    }
+}
@@ -0,0 +1,200 @@
+//! Lock-free SPSC slot ring over the shared audio mapping (Linux only)
+//!
+//! The v1 handshake ([`RACK_WINE_SHM_VERSION_FLAGS`]) forces a full spin-wait
+//! round trip per block: the client sets `client_ready`, the host sets
+//! `host_ready`, neither side can run ahead. This module implements the
+//! [`RACK_WINE_SHM_VERSION_RING`] layout instead — an SPSC ring of `num_slots`
+//! fixed-stride slots so the producer can publish the next input block while
+//! the consumer is still draining the current one.
+//!
+//! Publication follows the usual acquire/release discipline: the producer fills
+//! slot `write_index % num_slots`, then release-stores `write_index + 1`; the
+//! consumer acquire-loads `write_index`, and a value greater than its own
+//! `read_index` means the slot is fully written. Rather than burn a core
+//! polling, a waiter spins a few hundred times for the low-latency wakeup and
+//! then parks on the index word with `FUTEX_WAIT`, which the peer releases with
+//! a single `FUTEX_WAKE` after each publish.
+
+use crate::{Error, Result};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::protocol::{ShmHeader, ShmSlotHeader};
+
+/// Iterations to busy-spin before parking, trading a little CPU for the lowest
+/// possible wakeup latency on a hot stream.
+const SPIN_LIMIT: u32 = 400;
+
+/// A view over the ring embedded in a mapped audio buffer.
+///
+/// The client is the producer (it publishes input blocks and advances
+/// `write_index`); the Wine host is the consumer (it drains a slot, writes the
+/// processed output back in place, and advances `read_index`).
+pub(super) struct ShmRing {
+    base: *mut u8,
+    num_slots: u64,
+    slot_stride: usize,
+    /// Bytes of samples reserved for inputs at the front of each slot payload.
+    input_bytes: usize,
+}
+
+impl ShmRing {
+    /// Wrap a mapped buffer whose header has already been initialised with the
+    /// ring layout. `input_bytes`/`output_bytes` size the per-slot payload.
+    ///
+    /// # Safety
+    /// `base` must point to a live mapping of at least
+    /// `ShmHeader::SIZE + num_slots * slot_stride` bytes that outlives the ring.
+    pub(super) unsafe fn new(base: *mut u8, num_slots: u32, input_bytes: usize, output_bytes: usize) -> Self {
+        let slot_stride = ShmSlotHeader::SIZE + input_bytes + output_bytes;
+        Self {
+            base,
+            num_slots: num_slots as u64,
+            slot_stride,
+            input_bytes,
+        }
+    }
+
+    fn header(&self) -> &ShmHeader {
+        // Safety: the mapping starts with a valid ShmHeader (see `new`).
+        unsafe { &*(self.base as *const ShmHeader) }
+    }
+
+    fn write_index(&self) -> &AtomicU64 {
+        // Safety: the write_index field is 8-byte sized; on the little-endian
+        // x86_64 targets this host runs on, its bytes alias an AtomicU64.
+        unsafe { &*(std::ptr::addr_of!(self.header().write_index) as *const AtomicU64) }
+    }
+
+    fn read_index(&self) -> &AtomicU64 {
+        unsafe { &*(std::ptr::addr_of!(self.header().read_index) as *const AtomicU64) }
+    }
+
+    fn slot_ptr(&self, generation: u64) -> *mut u8 {
+        let slot = (generation % self.num_slots) as usize;
+        // Safety: slot < num_slots, so the offset stays within the mapping.
+        unsafe { self.base.add(ShmHeader::SIZE + slot * self.slot_stride) }
+    }
+
+    /// Publish `num_samples` worth of input already copied into the current
+    /// producer slot, returning the generation the caller should await.
+    ///
+    /// The payload must have been written via [`input_slot`](Self::input_slot)
+    /// for the same generation before calling this.
+    pub(super) fn publish(&self, num_samples: u32) -> u64 {
+        let gen = self.write_index().load(Ordering::Relaxed);
+        let slot = self.slot_ptr(gen);
+        // Safety: slot points at a valid ShmSlotHeader within the mapping.
+        unsafe {
+            let sh = &*(slot as *const ShmSlotHeader as *const AtomicU32);
+            sh.store(num_samples, Ordering::Relaxed);
+        }
+        // Release so the consumer's acquire-load of write_index observes the
+        // sample writes above.
+        self.write_index().store(gen + 1, Ordering::Release);
+        wake(self.write_index());
+        gen
+    }
+
+    /// Mutable view of the input region of the producer's current slot.
+    pub(super) fn input_slot(&self) -> &mut [u8] {
+        let gen = self.write_index().load(Ordering::Relaxed);
+        let slot = self.slot_ptr(gen);
+        // Safety: the input region follows the slot header and fits the stride.
+        unsafe { std::slice::from_raw_parts_mut(slot.add(ShmSlotHeader::SIZE), self.input_bytes) }
+    }
+
+    /// Read view of the output region of the slot published as `generation`.
+    pub(super) fn output_slot(&self, generation: u64) -> &[u8] {
+        let slot = self.slot_ptr(generation);
+        let off = ShmSlotHeader::SIZE + self.input_bytes;
+        let len = self.slot_stride - off;
+        // Safety: the output region follows the input region within the stride.
+        unsafe { std::slice::from_raw_parts(slot.add(off), len) }
+    }
+
+    /// Wait until the consumer has processed the slot published as `generation`,
+    /// i.e. `read_index > generation`, spinning then parking up to `timeout`.
+    ///
+    /// Returns [`Error::Other`] if the deadline elapses so a crashed host
+    /// surfaces instead of hanging the audio thread forever.
+    pub(super) fn wait_consumed(&self, generation: u64, timeout: Duration) -> Result<()> {
+        let target = generation + 1;
+        for _ in 0..SPIN_LIMIT {
+            if self.read_index().load(Ordering::Acquire) >= target {
+                return Ok(());
+            }
+            std::hint::spin_loop();
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let observed = self.read_index().load(Ordering::Acquire);
+            if observed >= target {
+                return Ok(());
+            }
+            let remaining = match deadline.checked_duration_since(Instant::now()) {
+                Some(d) if !d.is_zero() => d,
+                _ => return Err(Error::Other("Wine host audio ring timed out".to_string())),
+            };
+            // The futex compares the low 32 bits of the word; park until the
+            // consumer bumps read_index or the remaining budget elapses. A
+            // spurious wakeup just re-checks against the absolute deadline.
+            wait(self.read_index(), observed as u32, remaining);
+        }
+    }
+}
+
+/// `FUTEX_WAIT` on the low 32 bits of `word` for up to `timeout`.
+///
+/// Returns on wake, timeout, or a spurious `EAGAIN`/`EINTR`; the caller
+/// re-checks the value against its own deadline, so the specific outcome does
+/// not matter here.
+fn wait(word: &AtomicU64, expected: u32, timeout: Duration) {
+    futex_wait(word as *const AtomicU64 as *const u32, expected, timeout);
+}
+
+/// `FUTEX_WAKE` one waiter parked on the low 32 bits of `word`.
+fn wake(word: &AtomicU64) {
+    futex_wake(word as *const AtomicU64 as *const u32);
+}
+
+/// `FUTEX_WAIT` on the 32-bit word at `addr` for up to `timeout`.
+///
+/// # Safety
+/// `addr` must point at a live 32-bit value for the duration of the call.
+pub(super) fn futex_wait(addr: *const u32, expected: u32, timeout: Duration) {
+    let ts = libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    };
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            addr,
+            libc::FUTEX_WAIT,
+            expected,
+            &ts as *const libc::timespec,
+            std::ptr::null::<u32>(),
+            0u32,
+        );
+    }
+}
+
+/// `FUTEX_WAKE` one waiter parked on the 32-bit word at `addr`.
+///
+/// # Safety
+/// `addr` must point at a live 32-bit value for the duration of the call.
+pub(super) fn futex_wake(addr: *const u32) {
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            addr,
+            libc::FUTEX_WAKE,
+            1i32,
+            std::ptr::null::<libc::timespec>(),
+            std::ptr::null::<u32>(),
+            0u32,
+        );
+    }
+}
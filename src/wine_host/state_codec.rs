@@ -0,0 +1,233 @@
+//! Compression container for plugin state blobs
+//!
+//! `GetState`/`SetState` move opaque plugin "chunks" that can run to megabytes,
+//! and they were sent raw over the socket. This module wraps each blob in a
+//! small self-describing descriptor — a codec tag, the uncompressed size, and a
+//! CRC32 of the uncompressed bytes — so the receiver can verify integrity and
+//! fall back to raw when a codec was not compiled in. The optional zstd/LZMA
+//! backends mirror nod-rs's pluggable `compress-zstd`/`compress-lzma` features,
+//! so the dependencies are pulled in only when the corresponding feature is on.
+//!
+//! The client picks a codec the host advertised via the `GetInfo` capability
+//! flags ([`RACK_WINE_CAP_COMPRESS_ZSTD`](super::protocol::RACK_WINE_CAP_COMPRESS_ZSTD),
+//! …), so it never sends a format the peer cannot decode.
+
+use crate::{Error, Result};
+use zerocopy::byteorder::{LittleEndian, U32};
+use zerocopy::{AsBytes, FromBytes, FromZeroes, Ref, Unaligned};
+
+use super::protocol::{RACK_WINE_CAP_COMPRESS_LZMA, RACK_WINE_CAP_COMPRESS_ZSTD};
+
+/// Codec tag stored in the descriptor preceding a state blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub(super) enum Codec {
+    None = 0,
+    Zstd = 1,
+    Lzma = 2,
+}
+
+impl Codec {
+    /// The best codec both this build and the host (via `caps`) support,
+    /// preferring zstd, then LZMA, then raw.
+    pub(super) fn preferred(caps: u32) -> Codec {
+        if cfg!(feature = "compress-zstd") && caps & RACK_WINE_CAP_COMPRESS_ZSTD != 0 {
+            Codec::Zstd
+        } else if cfg!(feature = "compress-lzma") && caps & RACK_WINE_CAP_COMPRESS_LZMA != 0 {
+            Codec::Lzma
+        } else {
+            Codec::None
+        }
+    }
+
+    fn from_tag(tag: u32) -> Option<Codec> {
+        match tag {
+            0 => Some(Codec::None),
+            1 => Some(Codec::Zstd),
+            2 => Some(Codec::Lzma),
+            _ => None,
+        }
+    }
+}
+
+/// Fixed descriptor prefixing every encoded state blob.
+#[repr(C, packed)]
+#[derive(FromZeroes, FromBytes, AsBytes, Unaligned)]
+struct StateDescriptor {
+    /// Codec tag; see [`Codec`].
+    codec: U32<LittleEndian>,
+    /// Size of the blob after decompression, for pre-allocation and validation.
+    uncompressed_size: U32<LittleEndian>,
+    /// CRC32 (IEEE) of the uncompressed bytes.
+    crc32: U32<LittleEndian>,
+}
+
+impl StateDescriptor {
+    const SIZE: usize = std::mem::size_of::<Self>();
+}
+
+/// Wrap `data` in a descriptor, compressing with `codec` when available.
+///
+/// Falls back to [`Codec::None`] if the requested codec was not compiled in, so
+/// the output is always decodable by a receiver that shares this module.
+pub(super) fn encode(data: &[u8], codec: Codec) -> Vec<u8> {
+    let (tag, payload) = match codec {
+        Codec::Zstd => match compress_zstd(data) {
+            Some(bytes) => (Codec::Zstd, bytes),
+            None => (Codec::None, data.to_vec()),
+        },
+        Codec::Lzma => match compress_lzma(data) {
+            Some(bytes) => (Codec::Lzma, bytes),
+            None => (Codec::None, data.to_vec()),
+        },
+        Codec::None => (Codec::None, data.to_vec()),
+    };
+
+    let descriptor = StateDescriptor {
+        codec: U32::new(tag as u32),
+        uncompressed_size: U32::new(data.len() as u32),
+        crc32: U32::new(crc32(data)),
+    };
+
+    let mut out = Vec::with_capacity(StateDescriptor::SIZE + payload.len());
+    out.extend_from_slice(descriptor.as_bytes());
+    out.extend_from_slice(&payload);
+    out
+}
+
+/// Decode a blob produced by [`encode`], verifying its size and CRC32.
+pub(super) fn decode(buf: &[u8]) -> Result<Vec<u8>> {
+    let (descriptor, payload) = Ref::<_, StateDescriptor>::new_from_prefix(buf)
+        .ok_or_else(|| Error::Other("State blob shorter than its descriptor".to_string()))?;
+    let codec = Codec::from_tag(descriptor.codec.get())
+        .ok_or_else(|| Error::Other("Unknown state codec tag".to_string()))?;
+    let uncompressed_size = descriptor.uncompressed_size.get() as usize;
+
+    let data = match codec {
+        Codec::None => payload.to_vec(),
+        Codec::Zstd => decompress_zstd(payload, uncompressed_size)?,
+        Codec::Lzma => decompress_lzma(payload, uncompressed_size)?,
+    };
+
+    if data.len() != uncompressed_size {
+        return Err(Error::Other("State blob size mismatch after decode".to_string()));
+    }
+    if crc32(&data) != descriptor.crc32.get() {
+        return Err(Error::Other("State blob failed CRC32 check".to_string()));
+    }
+    Ok(data)
+}
+
+#[cfg(feature = "compress-zstd")]
+fn compress_zstd(data: &[u8]) -> Option<Vec<u8>> {
+    zstd::stream::encode_all(data, 3).ok()
+}
+
+#[cfg(not(feature = "compress-zstd"))]
+fn compress_zstd(_data: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "compress-zstd")]
+fn decompress_zstd(data: &[u8], hint: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(hint);
+    zstd::stream::copy_decode(data, &mut out)
+        .map_err(|e| Error::Other(format!("zstd decode failed: {}", e)))?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "compress-zstd"))]
+fn decompress_zstd(_data: &[u8], _hint: usize) -> Result<Vec<u8>> {
+    Err(Error::Other("State blob is zstd-compressed but compress-zstd is disabled".to_string()))
+}
+
+#[cfg(feature = "compress-lzma")]
+fn compress_lzma(data: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Read;
+    let mut encoder = xz2::read::XzEncoder::new(data, 6);
+    let mut out = Vec::new();
+    encoder.read_to_end(&mut out).ok().map(|_| out)
+}
+
+#[cfg(not(feature = "compress-lzma"))]
+fn compress_lzma(_data: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(feature = "compress-lzma")]
+fn decompress_lzma(data: &[u8], hint: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let mut decoder = xz2::read::XzDecoder::new(data);
+    let mut out = Vec::with_capacity(hint);
+    decoder
+        .read_to_end(&mut out)
+        .map_err(|e| Error::Other(format!("LZMA decode failed: {}", e)))?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "compress-lzma"))]
+fn decompress_lzma(_data: &[u8], _hint: usize) -> Result<Vec<u8>> {
+    Err(Error::Other("State blob is LZMA-compressed but compress-lzma is disabled".to_string()))
+}
+
+/// CRC32 (IEEE 802.3, reflected) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn none_codec_roundtrips() {
+        let data = b"some plugin chunk bytes".to_vec();
+        let blob = encode(&data, Codec::None);
+        assert_eq!(decode(&blob).unwrap(), data);
+    }
+
+    #[test]
+    fn empty_blob_roundtrips() {
+        let blob = encode(&[], Codec::None);
+        assert_eq!(decode(&blob).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn preferred_falls_back_to_none_without_caps() {
+        assert_eq!(Codec::preferred(0), Codec::None);
+    }
+
+    #[test]
+    fn requesting_zstd_still_roundtrips_without_the_feature() {
+        // Without `compress-zstd` compiled in, `encode` falls back to a raw
+        // blob tagged `Codec::None`; with the feature on it actually
+        // compresses. Either way the blob must still decode to the original.
+        let data = b"fallback payload".to_vec();
+        let blob = encode(&data, Codec::Zstd);
+        assert_eq!(decode(&blob).unwrap(), data);
+    }
+
+    #[test]
+    fn corrupted_payload_fails_crc_check() {
+        let data = b"some plugin chunk bytes".to_vec();
+        let mut blob = encode(&data, Codec::None);
+        let last = blob.len() - 1;
+        blob[last] ^= 0xFF;
+        assert!(decode(&blob).is_err());
+    }
+
+    #[test]
+    fn truncated_blob_fails_to_decode() {
+        let data = b"some plugin chunk bytes".to_vec();
+        let blob = encode(&data, Codec::None);
+        assert!(decode(&blob[..StateDescriptor::SIZE - 1]).is_err());
+    }
+}